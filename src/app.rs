@@ -0,0 +1,450 @@
+//! The Slint front end: the window, its callbacks and the settings it shows.
+
+use crate::controller::*;
+use crate::cpu::Difficulty;
+use crate::finesse::FinesseTraining;
+use crate::game::{Action, Game, Handling};
+use crate::replay::Replay;
+use crate::settings::{Bindings, Settings, ThemeMode, BOUND_ACTIONS};
+use crate::ui::*;
+use crate::versus::Versus;
+use crate::{cli, puzzle, skin, storage};
+use slint::{ModelRc, SharedString, VecModel};
+use std::{cell::RefCell, path::Path, rc::Rc, time::Duration};
+
+const DIG_RACE_ROWS: u16 = 10;
+const DIG_RACE_MESSINESS: f32 = 0.3;
+
+/// Opens the window and runs the game until it is closed.
+pub fn run() {
+    #[cfg(all(debug_assertions, target_arch = "wasm32"))]
+    console_error_panic_hook::set_once();
+
+    let args = Rc::new(cli::from_env());
+    let ui = AppWindow::new().unwrap();
+    let settings = match &args.config {
+        Some(path) => Settings::load_file(path).unwrap_or_else(|error| {
+            eprintln!("Could not read {}: {error}", path.display());
+            Settings::default()
+        }),
+        None => Settings::load(),
+    };
+    let settings = Rc::new(RefCell::new(settings));
+    if let Some(theme) = args.theme {
+        settings.borrow_mut().theme = theme;
+    }
+    ui.global::<Theme>()
+        .set_mode(settings.borrow().theme.index());
+    let game = Rc::new(RefCell::new(Game::with_options(args.game_options())));
+    let versus = Rc::new(RefCell::new(Versus::new(args.game_options())));
+
+    let skins = skin::load_all();
+    let skin_index = skins
+        .iter()
+        .position(|skin| skin.name == settings.borrow().skin)
+        .unwrap_or_default();
+    let skin = Rc::new(RefCell::new(skins[skin_index].clone()));
+    let skin_names: Vec<SharedString> = skins.iter().map(|s| s.name.as_str().into()).collect();
+    ui.global::<GameAdapter>()
+        .set_skins(ModelRc::new(VecModel::from(skin_names)));
+    ui.global::<GameAdapter>().set_skin_index(skin_index as i32);
+    apply_settings(&ui, &settings.borrow());
+    game_controller::set_skin(&ui, &skin.borrow());
+
+    let view = Rc::new(RefCell::new(game_controller::BoardView::new()));
+    let _game_controller = game_controller::setup(&ui, game.clone(), view.clone(), skin.clone());
+    let _versus_controller =
+        versus_controller::setup(&ui, versus.clone(), view.clone(), skin.clone());
+    let _demo_controller = demo_controller::setup(&ui, view, skin.clone());
+
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let settings_handle = settings.clone();
+    let args_handle = args.clone();
+    ui.global::<GameAdapter>().on_play_pressed(move || {
+        let ui = ui_handle.unwrap();
+        let game_adapter = ui.global::<GameAdapter>();
+        // A new game every time, so that its replay holds all it went through
+        game_handle.replace(Game::with_options(args_handle.game_options()));
+        start_playing(&game_adapter, &settings_handle.borrow(), &game_handle);
+    });
+
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let settings_handle = settings.clone();
+    let args_handle = args.clone();
+    ui.global::<GameAdapter>().on_dig_race_pressed(move || {
+        let ui = ui_handle.unwrap();
+        let game_adapter = ui.global::<GameAdapter>();
+        game_handle.replace(Game::new_dig_race_with_options(
+            args_handle.game_options(),
+            DIG_RACE_ROWS,
+            DIG_RACE_MESSINESS,
+        ));
+        start_playing(&game_adapter, &settings_handle.borrow(), &game_handle);
+    });
+
+    let ui_handle = ui.as_weak();
+    let versus_handle = versus.clone();
+    let args_handle = args.clone();
+    ui.global::<GameAdapter>().on_versus_pressed(move || {
+        let ui = ui_handle.unwrap();
+        versus_handle.replace(Versus::new(args_handle.game_options()));
+        start_versus(&ui.global::<GameAdapter>(), &versus_handle);
+    });
+
+    let ui_handle = ui.as_weak();
+    let versus_handle = versus.clone();
+    let args_handle = args.clone();
+    ui.global::<GameAdapter>().on_versus_cpu_pressed(move || {
+        let ui = ui_handle.unwrap();
+        let game_adapter = ui.global::<GameAdapter>();
+        let difficulty = Difficulty::from_index(game_adapter.get_cpu_difficulty());
+        versus_handle.replace(Versus::against_cpu(args_handle.game_options(), difficulty));
+        start_versus(&game_adapter, &versus_handle);
+    });
+
+    let ui_handle = ui.as_weak();
+    let versus_handle = versus.clone();
+    let args_handle = args.clone();
+    ui.global::<GameAdapter>().on_rematch_pressed(move || {
+        let ui = ui_handle.unwrap();
+        versus_handle
+            .borrow_mut()
+            .next_round(args_handle.game_options());
+        start_versus(&ui.global::<GameAdapter>(), &versus_handle);
+    });
+
+    let puzzles = puzzle::load_all();
+    let names: Vec<SharedString> = puzzles.iter().map(|p| p.name.as_str().into()).collect();
+    ui.global::<GameAdapter>()
+        .set_puzzles(ModelRc::new(VecModel::from(names)));
+
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let settings_handle = settings.clone();
+    let puzzle_count = puzzles.len();
+    ui.global::<GameAdapter>().on_puzzle_selected(move |index| {
+        let ui = ui_handle.unwrap();
+        let game_adapter = ui.global::<GameAdapter>();
+        if let Some(puzzle) = puzzles.get(index as usize) {
+            game_handle.replace(Game::new_puzzle(puzzle));
+            start_playing(&game_adapter, &settings_handle.borrow(), &game_handle);
+        }
+    });
+
+    let config = Rc::new(args.config.clone());
+    let ui_handle = ui.as_weak();
+    let settings_handle = settings.clone();
+    let config_handle = config.clone();
+    ui.global::<GameAdapter>().on_settings_changed(move || {
+        let ui = ui_handle.unwrap();
+        let mut settings = settings_handle.borrow_mut();
+        settings.theme = ThemeMode::from_index(ui.global::<Theme>().get_mode());
+        settings.patterns = ui.global::<BlockSkin>().get_patterns();
+        settings.grid_lines = ui.global::<BlockSkin>().get_grid_lines();
+        let game_adapter = ui.global::<GameAdapter>();
+        settings.line_clear_delay = game_adapter.get_line_clear_delay() as u32;
+        settings.entry_delay = game_adapter.get_entry_delay() as u32;
+        settings.handling = handling(&game_adapter);
+        settings.ghost = game_adapter.get_ghost();
+        settings.preview_count = game_adapter.get_preview_count() as u32;
+        save_settings(&ui, &settings, config_handle.as_deref());
+    });
+
+    let ui_handle = ui.as_weak();
+    let settings_handle = settings.clone();
+    let config_handle = config.clone();
+    ui.global::<GameAdapter>()
+        .on_key_bound(move |index, key_text| {
+            let key = key_text.chars().next().filter(|key| !key.is_control());
+            let index = index as usize;
+            let action = BOUND_ACTIONS.get(index % BOUND_ACTIONS.len());
+            if let (Some(&action), Some(key)) = (action, key) {
+                let mut settings = settings_handle.borrow_mut();
+                // The single player keys, then those of each versus player
+                match index / BOUND_ACTIONS.len() {
+                    0 => settings.bindings.bind(action, key),
+                    player @ 1..=2 => settings.versus_bindings[player - 1].bind(action, key),
+                    _ => return,
+                }
+                let ui = ui_handle.unwrap();
+                set_bindings(&ui, &settings);
+                save_settings(&ui, &settings, config_handle.as_deref());
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    let settings_handle = settings.clone();
+    ui.global::<GameAdapter>().on_skin_selected(move |index| {
+        let ui = ui_handle.unwrap();
+        if let Some(selected) = skins.get(index as usize) {
+            skin.replace(selected.clone());
+            game_controller::set_skin(&ui, selected);
+            ui.global::<GameAdapter>().set_skin_index(index);
+            let mut settings = settings_handle.borrow_mut();
+            settings.skin = selected.name.clone();
+            save_settings(&ui, &settings, config.as_deref());
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let versus_handle = versus.clone();
+    let settings_handle = settings.clone();
+    ui.on_key_pressed(move |key_text: SharedString| {
+        let ui = ui_handle.unwrap();
+        let game_adapter = ui.global::<GameAdapter>();
+        if game_adapter.get_demo() {
+            game_adapter.set_demo(false);
+            return;
+        }
+        // A keyboard is in use, on-screen controls are not needed
+        game_adapter.set_touch_controls(false);
+        if game_adapter.get_versus() {
+            if game_adapter.get_playing() {
+                let mut versus = versus_handle.borrow_mut();
+                for (player, action) in
+                    versus_actions(&settings_handle.borrow(), &versus, &key_text)
+                {
+                    versus.game_mut(player).press(action);
+                }
+            }
+            return;
+        }
+        // Keys on the title screen are not meant for the game behind it
+        if !game_adapter.get_playing() || game_handle.borrow().is_replaying() {
+            return;
+        }
+        if let Some(action) = key_action(&settings_handle.borrow().bindings, &key_text) {
+            game_handle.borrow_mut().press(action);
+        }
+    });
+
+    ui.global::<GameAdapter>()
+        .set_touch_controls(has_touch_screen());
+
+    // On-screen controls play player 1 in local versus
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let versus_handle = versus.clone();
+    ui.global::<GameAdapter>().on_action_pressed(move |action| {
+        let ui = ui_handle.unwrap();
+        let game_adapter = ui.global::<GameAdapter>();
+        if !game_adapter.get_playing() {
+            return;
+        }
+        if game_adapter.get_versus() {
+            versus_handle
+                .borrow_mut()
+                .game_mut(0)
+                .press(game_action(action));
+        } else if !game_handle.borrow().is_replaying() {
+            game_handle.borrow_mut().press(game_action(action));
+        }
+    });
+
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let versus_handle = versus.clone();
+    ui.global::<GameAdapter>()
+        .on_action_released(move |action| {
+            if ui_handle.unwrap().global::<GameAdapter>().get_versus() {
+                versus_handle
+                    .borrow_mut()
+                    .game_mut(0)
+                    .release(game_action(action));
+            } else if !game_handle.borrow().is_replaying() {
+                game_handle.borrow_mut().release(game_action(action));
+            }
+        });
+
+    let ui_handle = ui.as_weak();
+    let game_handle = game.clone();
+    let settings_handle = settings.clone();
+    ui.on_key_released(move |key_text: SharedString| {
+        if ui_handle.unwrap().global::<GameAdapter>().get_versus() {
+            let mut versus = versus.borrow_mut();
+            for (player, action) in versus_actions(&settings_handle.borrow(), &versus, &key_text) {
+                versus.game_mut(player).release(action);
+            }
+            return;
+        }
+        if game_handle.borrow().is_replaying() {
+            return;
+        }
+        if let Some(action) = key_action(&settings_handle.borrow().bindings, &key_text) {
+            game_handle.borrow_mut().release(action);
+        }
+    });
+
+    // Start the game asked for on the command line
+    let game_adapter = ui.global::<GameAdapter>();
+    if let Some(path) = &args.replay {
+        let replay = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| Replay::parse(&source).map_err(|error| error.to_string()));
+        match replay {
+            Ok(replay) => {
+                game.replace(replay.start());
+                start_playing(&game_adapter, &settings.borrow(), &game);
+            }
+            Err(error) => {
+                eprintln!("Could not read {}: {error}", path.display());
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(mode) = args.mode {
+        match mode {
+            cli::StartMode::Marathon => game_adapter.invoke_play_pressed(),
+            cli::StartMode::DigRace => game_adapter.invoke_dig_race_pressed(),
+            cli::StartMode::Puzzle(index) if index < puzzle_count => {
+                game_adapter.invoke_puzzle_selected(index as i32)
+            }
+            cli::StartMode::Puzzle(_) => {
+                eprintln!("There are only {puzzle_count} puzzles");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    ui.run().unwrap();
+}
+
+fn game_action(action: GameAction) -> Action {
+    match action {
+        GameAction::MoveLeft => Action::MoveLeft,
+        GameAction::MoveRight => Action::MoveRight,
+        GameAction::RotateRight => Action::RotateRight,
+        GameAction::RotateLeft => Action::RotateLeft,
+        GameAction::RotateHalf => Action::RotateHalf,
+        GameAction::SoftDrop => Action::SoftDrop,
+        GameAction::HardDrop => Action::HardDrop,
+        GameAction::Hold => Action::Hold,
+    }
+}
+
+/// Returns true on touch devices, where the on-screen controls are shown
+/// until a key is pressed.
+#[cfg(target_arch = "wasm32")]
+fn has_touch_screen() -> bool {
+    web_sys::window().is_some_and(|window| window.navigator().max_touch_points() > 0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn has_touch_screen() -> bool {
+    false
+}
+
+fn key_action(bindings: &Bindings, key_text: &str) -> Option<Action> {
+    key_text.chars().next().and_then(|key| bindings.action(key))
+}
+
+/// Returns the actions a key gives each player of versus. Alone against the
+/// computer, player 1 plays with the single player keys.
+fn versus_actions(settings: &Settings, versus: &Versus, key_text: &str) -> Vec<(usize, Action)> {
+    if versus.has_cpu() {
+        return key_action(&settings.bindings, key_text)
+            .map(|action| vec![(0, action)])
+            .unwrap_or_default();
+    }
+    settings
+        .versus_bindings
+        .iter()
+        .enumerate()
+        .filter_map(|(player, bindings)| Some((player, key_action(bindings, key_text)?)))
+        .collect()
+}
+
+/// Shows the stored settings in the window.
+fn apply_settings(ui: &AppWindow, settings: &Settings) {
+    let block_skin = ui.global::<BlockSkin>();
+    block_skin.set_patterns(settings.patterns);
+    block_skin.set_grid_lines(settings.grid_lines);
+
+    let game_adapter = ui.global::<GameAdapter>();
+    game_adapter.set_line_clear_delay(settings.line_clear_delay as i32);
+    game_adapter.set_entry_delay(settings.entry_delay as i32);
+    game_adapter.set_das(settings.handling.das as i32);
+    game_adapter.set_arr(settings.handling.arr as i32);
+    game_adapter.set_soft_drop(settings.handling.soft_drop as i32);
+    game_adapter.set_ghost(settings.ghost);
+    game_adapter.set_preview_count(settings.preview_count as i32);
+    set_bindings(ui, settings);
+}
+
+/// Shows the keys of a single player, then those of each versus player.
+fn set_bindings(ui: &AppWindow, settings: &Settings) {
+    let labels: Vec<SharedString> = std::iter::once(&settings.bindings)
+        .chain(&settings.versus_bindings)
+        .flat_map(|bindings| {
+            BOUND_ACTIONS
+                .iter()
+                .map(|&action| bindings.label(action).into())
+        })
+        .collect();
+    ui.global::<GameAdapter>()
+        .set_bindings(ModelRc::new(VecModel::from(labels)));
+}
+
+fn handling(game_adapter: &GameAdapter) -> Handling {
+    Handling {
+        das: game_adapter.get_das().max(0) as u32,
+        arr: game_adapter.get_arr().max(0) as u32,
+        soft_drop: game_adapter.get_soft_drop().max(0) as u32,
+    }
+}
+
+/// Saves the settings to the file given on the command line, or to the
+/// default location. A failure is reported and shown on the title screen.
+fn save_settings(ui: &AppWindow, settings: &Settings, path: Option<&Path>) {
+    let saved = match path {
+        Some(path) => settings.save_file(path),
+        None => settings.save(),
+    };
+    if let Err(error) = saved {
+        let message = format!("Could not save settings: {error}");
+        storage::report(&message);
+        ui.global::<GameAdapter>().set_message(message.into());
+    }
+}
+
+fn start_playing(game_adapter: &GameAdapter, settings: &Settings, game: &RefCell<Game>) {
+    let training = match game_adapter.get_finesse_training() {
+        1 => FinesseTraining::Flash,
+        2 => FinesseTraining::Restart,
+        _ => FinesseTraining::Off,
+    };
+    let line_clear_delay = Duration::from_millis(game_adapter.get_line_clear_delay() as u64);
+    let entry_delay = Duration::from_millis(game_adapter.get_entry_delay() as u64);
+    // A replay keeps the settings it was recorded with
+    if !game.borrow().is_replaying() {
+        let mut game = game.borrow_mut();
+        game.set_finesse_training(training);
+        game.set_finesse_half_turns(settings.bindings.is_bound(Action::RotateHalf));
+        game.set_line_clear_delay(line_clear_delay);
+        game.set_entry_delay(entry_delay);
+        game.set_handling(handling(game_adapter));
+        game.set_recording(true);
+    }
+    // The time spent on the title screen is not played
+    game.borrow_mut().start_clock();
+    game_adapter.set_message(SharedString::new());
+    game_adapter.set_game_over(false);
+    game_adapter.set_versus(false);
+    game_adapter.set_playing(true);
+}
+
+fn start_versus(game_adapter: &GameAdapter, versus: &RefCell<Versus>) {
+    let line_clear_delay = Duration::from_millis(game_adapter.get_line_clear_delay() as u64);
+    let entry_delay = Duration::from_millis(game_adapter.get_entry_delay() as u64);
+    versus
+        .borrow_mut()
+        .configure(handling(game_adapter), line_clear_delay, entry_delay);
+    game_adapter.set_versus_cpu(versus.borrow().has_cpu());
+    game_adapter.set_message(SharedString::new());
+    game_adapter.set_game_over(false);
+    game_adapter.set_round_over_show(false);
+    game_adapter.set_versus(true);
+    game_adapter.set_playing(true);
+}
@@ -0,0 +1,213 @@
+//! Terminal front end, playing the same engine as the Slint window.
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::io::Result<()> {
+    terminal::run()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod terminal {
+    use crossterm::{
+        cursor,
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute, queue,
+        style::{self, Stylize},
+        terminal,
+    };
+    use lib_tetris_slint::{
+        game::{Action, Game},
+        pieces::{Piece, PieceKind},
+        skin::Skin,
+        utils::Vector2,
+    };
+    use std::{
+        io::{self, Write},
+        time::Duration,
+    };
+
+    /// Time to wait for input before updating the game.
+    const FRAME: Duration = Duration::from_millis(16);
+    /// Column of the side panel, right of the grid.
+    const PANEL_X: u16 = Game::GRID_WIDTH * 2 + 4;
+
+    /// Restores the terminal when dropped, even after a panic.
+    struct RawTerminal;
+
+    impl RawTerminal {
+        fn enter() -> io::Result<Self> {
+            terminal::enable_raw_mode()?;
+            execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+            Ok(RawTerminal)
+        }
+    }
+
+    impl Drop for RawTerminal {
+        fn drop(&mut self) {
+            let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+
+    pub fn run() -> io::Result<()> {
+        let _raw = RawTerminal::enter()?;
+        let mut stdout = io::stdout();
+        let mut game = Game::new();
+
+        loop {
+            if event::poll(FRAME)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    if is_quit(&key) {
+                        return Ok(());
+                    }
+                    if game.is_game_over() {
+                        if key.code == KeyCode::Enter {
+                            game = Game::new();
+                        }
+                    } else if let Some(action) = key_action(&key) {
+                        // Terminals report no key releases, so held keys
+                        // repeat through the terminal instead of auto shift
+                        if action == Action::HardDrop {
+                            game.press(action);
+                        } else {
+                            game.apply(action);
+                        }
+                    }
+                }
+            }
+            if !game.is_game_over() {
+                game.update();
+            }
+            // The terminal does not animate events
+            game.take_events();
+            draw(&mut stdout, &game)?;
+        }
+    }
+
+    fn is_quit(key: &KeyEvent) -> bool {
+        key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+    }
+
+    fn key_action(key: &KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Left => Some(Action::MoveLeft),
+            KeyCode::Right => Some(Action::MoveRight),
+            KeyCode::Up => Some(Action::RotateRight),
+            KeyCode::Down => Some(Action::SoftDrop),
+            KeyCode::Char(c) => Action::from_key(c),
+            _ => None,
+        }
+    }
+
+    fn draw(out: &mut impl Write, game: &Game) -> io::Result<()> {
+        queue!(out, cursor::MoveTo(0, 0))?;
+
+        // Grid, with the current piece drawn over it once it spawned
+        let mut grid = game.get_grid().clone();
+        let current = game.get_current();
+        for cell in current.cells() {
+            if cell.x >= 0 && cell.y >= 0 && !game.is_between_pieces() {
+                grid[cell.y as usize][cell.x as usize] = Some(current.piece.kind);
+            }
+        }
+        let border = "#".repeat(Game::GRID_WIDTH as usize * 2 + 2);
+        queue!(out, style::Print(&border))?;
+        for (y, row) in grid.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, y as u16 + 1), style::Print("#"))?;
+            for cell in row {
+                draw_cell(out, *cell)?;
+            }
+            queue!(out, style::Print("#"))?;
+        }
+        queue!(
+            out,
+            cursor::MoveTo(0, Game::GRID_HEIGHT + 1),
+            style::Print(&border)
+        )?;
+
+        // Side panel, as tall as the pieces it shows
+        queue!(out, cursor::MoveTo(PANEL_X, 1), style::Print("NEXT"))?;
+        let y = draw_piece(out, game.get_next(), 2)?;
+        clear_line(out, y)?;
+        queue!(out, cursor::MoveTo(PANEL_X, y + 1), style::Print("HOLD"))?;
+        let y = draw_piece(out, game.get_held().as_ref(), y + 2)?;
+        clear_line(out, y)?;
+        queue!(
+            out,
+            cursor::MoveTo(PANEL_X, y + 1),
+            style::Print(format!("SCORE {:<8}", game.get_score())),
+            cursor::MoveTo(PANEL_X, y + 2),
+            style::Print(format!("LINES {:<8}", game.get_lines())),
+        )?;
+        clear_line(out, y + 3)?;
+        clear_line(out, y + 4)?;
+        if game.is_game_over() {
+            queue!(
+                out,
+                style::Print("GAME OVER".bold()),
+                cursor::MoveTo(PANEL_X, y + 5),
+                style::Print("Enter: restart  Esc: quit"),
+            )?;
+        } else {
+            clear_line(out, y + 5)?;
+            queue!(out, style::Print("Esc: quit"))?;
+        }
+        queue!(out, terminal::Clear(terminal::ClearType::UntilNewLine))?;
+        // Rows left over from taller pieces
+        for y in y + 6..Game::GRID_HEIGHT + 2 {
+            clear_line(out, y)?;
+        }
+
+        out.flush()
+    }
+
+    /// Draws a piece from the top left of its own box, in at least the space
+    /// of a tetromino, and returns the row below it.
+    fn draw_piece(out: &mut impl Write, piece: Option<&Piece>, y: u16) -> io::Result<u16> {
+        let (corner, size) = piece.map_or((Vector2::new(0, 0), Vector2::new(0, 0)), |piece| {
+            piece.bounding_box(0)
+        });
+        let mut rows = vec![vec![None; size.x.max(4) as usize]; size.y.max(2) as usize];
+        if let Some(piece) = piece {
+            for cell in piece.get_shape(0).iter().map(|cell| cell.sub(corner)) {
+                rows[cell.y as usize][cell.x as usize] = Some(piece.kind);
+            }
+        }
+        for (i, row) in rows.iter().enumerate() {
+            queue!(out, cursor::MoveTo(PANEL_X, y + i as u16))?;
+            for cell in row {
+                draw_cell(out, *cell)?;
+            }
+            queue!(out, terminal::Clear(terminal::ClearType::UntilNewLine))?;
+        }
+
+        Ok(y + rows.len() as u16)
+    }
+
+    /// Clears a row of the side panel and leaves the cursor at its start.
+    fn clear_line(out: &mut impl Write, y: u16) -> io::Result<()> {
+        queue!(
+            out,
+            cursor::MoveTo(PANEL_X, y),
+            terminal::Clear(terminal::ClearType::UntilNewLine)
+        )
+    }
+
+    fn draw_cell(out: &mut impl Write, cell: Option<PieceKind>) -> io::Result<()> {
+        match cell {
+            Some(kind) => queue!(out, style::PrintStyledContent("  ".on(term_color(kind)))),
+            None => queue!(out, style::Print(" .")),
+        }
+    }
+
+    fn term_color(kind: PieceKind) -> style::Color {
+        let (r, g, b) = Skin::default().rgb(kind);
+        style::Color::Rgb { r, g, b }
+    }
+}
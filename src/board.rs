@@ -0,0 +1,306 @@
+//! The cells of the game grid, packed for fast checks: one bit mask per row
+//! tells which cells are filled, and what fills them is kept beside it for
+//! drawing.
+
+use crate::game::Grid;
+use crate::garbage::Row;
+use crate::pieces::{PhysicalPiece, PieceKind, MAX_CELLS};
+
+/// The filled cells of a grid and what fills them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board {
+    /// Filled cells of each row, bit `x` standing for column `x`.
+    rows: Vec<u64>,
+    /// What fills the cells, indexed by row then column.
+    cells: Grid,
+    /// Mask of a row with every cell filled.
+    full: u64,
+}
+
+impl Board {
+    /// Creates an empty board.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The number of columns, at most 64.
+    /// * `height` - The number of rows.
+    ///
+    /// # Returns
+    ///
+    /// * `Board` - A new empty board.
+    pub fn new(width: u16, height: u16) -> Board {
+        Board::from_grid(vec![vec![None; width as usize]; height as usize])
+    }
+
+    /// Creates a board holding the cells of a grid.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid` - The cells, indexed by row then column.
+    ///
+    /// # Returns
+    ///
+    /// * `Board` - A new board.
+    pub fn from_grid(grid: Grid) -> Board {
+        let width = grid.first().map_or(0, Vec::len);
+        Board {
+            rows: grid.iter().map(|row| mask(row)).collect(),
+            full: u64::MAX >> (64 - width),
+            cells: grid,
+        }
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> u16 {
+        self.full.count_ones() as u16
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> u16 {
+        self.rows.len() as u16
+    }
+
+    /// Returns what fills the cells, indexed by row then column.
+    pub fn cells(&self) -> &Grid {
+        &self.cells
+    }
+
+    /// Returns the filled cells of each row, from top to bottom, bit `x`
+    /// standing for column `x`.
+    pub fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    /// Returns what fills a cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column of the cell.
+    /// * `y` - The row of the cell.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PieceKind>` - The kind of the cell, or None if it is empty.
+    pub fn get(&self, x: u16, y: u16) -> Option<PieceKind> {
+        self.cells[y as usize][x as usize]
+    }
+
+    /// Fills or empties a cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column of the cell.
+    /// * `y` - The row of the cell.
+    /// * `kind` - What fills the cell, or None to empty it.
+    pub fn set(&mut self, x: u16, y: u16, kind: Option<PieceKind>) {
+        self.cells[y as usize][x as usize] = kind;
+        match kind {
+            Some(_) => self.rows[y as usize] |= 1 << x,
+            None => self.rows[y as usize] &= !(1 << x),
+        }
+    }
+
+    /// Replaces a whole row.
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - The row to replace.
+    /// * `row` - The new cells, as many as the board has columns.
+    pub fn set_row(&mut self, y: u16, row: Row) {
+        self.rows[y as usize] = mask(&row);
+        self.cells[y as usize] = row;
+    }
+
+    /// Returns true if a cell is outside the walls or the floor, or filled.
+    /// Cells above the top of the board are free.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The column of the cell.
+    /// * `y` - The row of the cell.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the cell is blocked.
+    pub fn is_blocked(&self, x: i16, y: i16) -> bool {
+        if x < 0 || x >= self.width() as i16 || y >= self.height() as i16 {
+            true
+        } else {
+            y >= 0 && self.rows[y as usize] & (1 << x) != 0
+        }
+    }
+
+    /// Returns true if the piece overlaps the walls, the floor or a filled
+    /// cell, testing each of its rows against the board with one mask.
+    ///
+    /// # Arguments
+    ///
+    /// * `piece` - The piece to test.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the piece collides, otherwise false.
+    pub fn collides(&self, piece: &PhysicalPiece) -> bool {
+        // The cells of each row of the piece, relative to its column
+        let mut shape = [0u64; MAX_CELLS];
+        for cell in piece.get_shape() {
+            shape[cell.y as usize] |= 1 << cell.x;
+        }
+
+        shape.iter().enumerate().any(|(dy, &cells)| {
+            if cells == 0 {
+                return false;
+            }
+            let cells = if piece.x < 0 {
+                // Cells shifted out past the left wall
+                if cells & ((1 << -piece.x) - 1) != 0 {
+                    return true;
+                }
+                cells >> -piece.x
+            } else {
+                cells << piece.x
+            };
+            let y = piece.y + dy as i16;
+            cells & !self.full != 0
+                || y >= self.height() as i16
+                || (y >= 0 && self.rows[y as usize] & cells != 0)
+        })
+    }
+
+    /// Returns the rows with every cell filled, from top to bottom.
+    pub fn full_rows(&self) -> Vec<u16> {
+        (0..self.height())
+            .filter(|&y| self.rows[y as usize] == self.full)
+            .collect()
+    }
+
+    /// Removes a row, moving the rows above it down and adding an empty row
+    /// at the top.
+    ///
+    /// # Arguments
+    ///
+    /// * `y` - The row to remove.
+    ///
+    /// # Returns
+    ///
+    /// * `Row` - The cells of the removed row.
+    pub fn remove_row(&mut self, y: u16) -> Row {
+        self.rows.remove(y as usize);
+        self.rows.insert(0, 0);
+        let row = self.cells.remove(y as usize);
+        self.cells.insert(0, vec![None; row.len()]);
+        row
+    }
+
+    /// Pushes rows up from the bottom, dropping as many rows from the top.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The rows to add, from top to bottom, at most as many as the
+    ///   board has.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if a filled cell was pushed out of the top.
+    pub fn push_up(&mut self, rows: Vec<Row>) -> bool {
+        let count = rows.len();
+        let topped_out = self.rows.drain(..count).any(|row| row != 0);
+        self.cells.drain(..count);
+        self.rows.extend(rows.iter().map(|row| mask(row)));
+        self.cells.extend(rows);
+        topped_out
+    }
+
+    /// Returns true if no cell is filled.
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+}
+
+fn mask(row: &[Option<PieceKind>]) -> u64 {
+    row.iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.is_some())
+        .fold(0, |mask, (x, _)| mask | 1 << x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pieces::{BLOCK_I, BLOCK_O};
+
+    fn piece(x: i16, y: i16) -> PhysicalPiece {
+        PhysicalPiece {
+            x,
+            y,
+            rotation: 0,
+            piece: BLOCK_O,
+        }
+    }
+
+    #[test]
+    fn test_masks_follow_cells() {
+        let mut board = Board::new(10, 20);
+        board.set(3, 19, Some(PieceKind::Z));
+        assert_eq!(board.rows()[19], 1 << 3);
+        assert_eq!(board.get(3, 19), Some(PieceKind::Z));
+        board.set(3, 19, None);
+        assert!(board.is_empty());
+        assert_eq!(board, Board::new(10, 20));
+    }
+
+    #[test]
+    fn test_collides() {
+        let mut board = Board::new(10, 20);
+        let shape = piece(0, 0).cells();
+        let left = shape.iter().map(|cell| cell.x).min().unwrap();
+        let right = shape.iter().map(|cell| cell.x).max().unwrap();
+        let bottom = shape.iter().map(|cell| cell.y).max().unwrap();
+
+        assert!(!board.collides(&piece(-left, 0)));
+        assert!(board.collides(&piece(-left - 1, 0)));
+        assert!(!board.collides(&piece(9 - right, 0)));
+        assert!(board.collides(&piece(10 - right, 0)));
+        assert!(!board.collides(&piece(0, 19 - bottom)));
+        assert!(board.collides(&piece(0, 20 - bottom)));
+        // Above the top is free
+        assert!(!board.collides(&piece(0, -bottom)));
+
+        board.set((left + 1) as u16, 19, Some(PieceKind::Z));
+        assert!(board.collides(&piece(1, 19 - bottom)));
+        assert!(!board.collides(&piece(3, 19 - bottom)));
+        let i = PhysicalPiece {
+            piece: BLOCK_I,
+            ..piece(0, 0)
+        };
+        assert_eq!(
+            board.collides(&i),
+            i.cells()
+                .iter()
+                .any(|cell| board.is_blocked(cell.x, cell.y))
+        );
+    }
+
+    #[test]
+    fn test_clear_and_push_up() {
+        let mut board = Board::new(4, 4);
+        board.set_row(3, vec![Some(PieceKind::Z); 4]);
+        board.set(0, 2, Some(PieceKind::J));
+        assert_eq!(board.full_rows(), vec![3]);
+
+        assert_eq!(board.remove_row(3), vec![Some(PieceKind::Z); 4]);
+        assert_eq!(board.get(0, 3), Some(PieceKind::J));
+        assert!(board.full_rows().is_empty());
+
+        let row = vec![
+            Some(PieceKind::Garbage),
+            None,
+            Some(PieceKind::Garbage),
+            None,
+        ];
+        assert!(!board.push_up(vec![row.clone(); 2]));
+        assert_eq!(board.rows(), &[0, 1, 0b0101, 0b0101]);
+        assert_eq!(board.cells()[3], row);
+        assert!(board.push_up(vec![row; 3]));
+    }
+}
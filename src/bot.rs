@@ -0,0 +1,252 @@
+use crate::board::Board;
+use crate::game::{Action, Game};
+use std::time::Duration;
+
+/// Number of the best placements scored again with the next pieces when
+/// looking ahead.
+const LOOKAHEAD_WIDTH: usize = 8;
+
+/// Weights of the placement heuristic. Heights, holes and bumpiness are
+/// penalties, cleared lines a reward.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    pub height: f32,
+    pub lines: f32,
+    pub holes: f32,
+    pub bumpiness: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            height: -0.510066,
+            lines: 0.760666,
+            holes: -0.35663,
+            bumpiness: -0.184483,
+        }
+    }
+}
+
+/// A final position for the current piece, with the inputs leading to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub inputs: Vec<Action>,
+    pub score: f32,
+}
+
+/// A player that picks placements with a weighted board heuristic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bot {
+    pub weights: Weights,
+    /// Number of next pieces placed ahead when scoring a placement, 0 to
+    /// score the placement alone.
+    pub lookahead: usize,
+}
+
+impl Bot {
+    /// Evaluates every reachable placement of the current and held pieces.
+    /// When looking ahead, only the `LOOKAHEAD_WIDTH` placements leaving the
+    /// best grids are kept and scored again after the next pieces.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - The game to play in.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Placement>` - The reachable placements with their score.
+    pub fn placements(&self, game: &Game) -> Vec<Placement> {
+        let mut placements = Vec::new();
+        let mut starts = vec![(game.clone(), Vec::new())];
+        if game.can_hold() {
+            let mut held = game.clone();
+            held.apply(Action::Hold);
+            starts.push((held, vec![Action::Hold]));
+        }
+        for (start, prefix) in starts {
+            for reachable in start.reachable_placements() {
+                let mut inputs = prefix.clone();
+                inputs.extend(reachable.inputs);
+                placements.push(Placement {
+                    score: self.simulate(&start, &inputs[prefix.len()..], 0),
+                    inputs,
+                });
+            }
+        }
+        if self.lookahead > 0 {
+            placements.sort_by(|a, b| b.score.total_cmp(&a.score));
+            placements.truncate(LOOKAHEAD_WIDTH);
+            for placement in &mut placements {
+                let (start, inputs) = match placement.inputs.split_first() {
+                    Some((Action::Hold, inputs)) => {
+                        let mut held = game.clone();
+                        held.apply(Action::Hold);
+                        (held, inputs)
+                    }
+                    _ => (game.clone(), &placement.inputs[..]),
+                };
+                placement.score = self.simulate(&start, inputs, self.lookahead);
+            }
+        }
+
+        placements
+    }
+
+    /// Returns the best placement for the current piece.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - The game to play in.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Placement>` - The best placement, or None if there is none.
+    pub fn best_placement(&self, game: &Game) -> Option<Placement> {
+        self.placements(game)
+            .into_iter()
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+
+    /// Returns the inputs that lead to the best placement.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - The game to play in.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Action>` - The inputs, empty if no placement was found.
+    pub fn best_inputs(&self, game: &Game) -> Vec<Action> {
+        self.best_placement(game)
+            .map(|placement| placement.inputs)
+            .unwrap_or_default()
+    }
+
+    /// Plays the inputs on a copy of the game and scores the resulting grid,
+    /// after the best placements of the next `lookahead` pieces.
+    fn simulate(&self, game: &Game, inputs: &[Action], lookahead: usize) -> f32 {
+        let mut game = game.clone();
+        // Copies are never drawn
+        game.take_events();
+        game.take_dirty_cells();
+        // Lines are cleared and the next piece spawns at once
+        game.set_line_clear_delay(Duration::ZERO);
+        game.set_entry_delay(Duration::ZERO);
+        // Finesse is only checked on the real game
+        game.set_finesse_checked(false);
+        for &action in inputs {
+            game.apply(action);
+        }
+        game.tick();
+        if game.is_game_over() && !game.is_completed() {
+            return f32::NEG_INFINITY;
+        }
+        let lines = game.get_last_clear().lines;
+        if lookahead == 0 || game.is_game_over() {
+            return self.evaluate(game.get_board(), lines);
+        }
+
+        let best = game
+            .reachable_placements()
+            .iter()
+            .map(|reachable| self.simulate(&game, &reachable.inputs, lookahead - 1))
+            .fold(f32::NEG_INFINITY, f32::max);
+        self.weights.lines * lines as f32 + best
+    }
+
+    fn evaluate(&self, board: &Board, lines: u8) -> f32 {
+        let height = board.height() as usize;
+        let mut heights = vec![0usize; board.width() as usize];
+        let mut holes = 0usize;
+        // Columns with a filled cell in a row above
+        let mut covered = 0u64;
+        for (y, &row) in board.rows().iter().enumerate() {
+            let mut tops = row & !covered;
+            while tops != 0 {
+                heights[tops.trailing_zeros() as usize] = height - y;
+                tops &= tops - 1;
+            }
+            holes += (covered & !row).count_ones() as usize;
+            covered |= row;
+        }
+        let aggregate: usize = heights.iter().sum();
+        let bumpiness: usize = heights
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]))
+            .sum();
+
+        self.weights.height * aggregate as f32
+            + self.weights.lines * lines as f32
+            + self.weights.holes * holes as f32
+            + self.weights.bumpiness * bumpiness as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameOptions;
+
+    #[test]
+    fn test_placements_include_hold() {
+        let bot = Bot::default();
+        let game = Game::new();
+        let placements = bot.placements(&game);
+        assert!(placements.iter().any(|p| p.inputs[0] == Action::Hold));
+        assert!(placements.iter().any(|p| p.inputs[0] != Action::Hold));
+    }
+
+    #[test]
+    fn test_evaluate_prefers_flat_grid() {
+        let bot = Bot::default();
+        let mut board = Board::new(Game::GRID_WIDTH, Game::GRID_HEIGHT);
+        let flat = bot.evaluate(&board, 0);
+        board.set(0, Game::GRID_HEIGHT - 2, Some(crate::pieces::PieceKind::Z));
+        assert!(bot.evaluate(&board, 0) < flat);
+    }
+
+    /// Returns a game with a fixed seed, for the bot to play the same pieces
+    /// on every run.
+    fn seeded_game() -> Game {
+        Game::with_options(GameOptions {
+            seed: 1,
+            ..GameOptions::default()
+        })
+    }
+
+    /// Plays pieces until the game is over or the piece limit is reached,
+    /// returning the number of pieces placed.
+    fn play(bot: &Bot, game: &mut Game, max_pieces: usize) -> usize {
+        let mut pieces = 0;
+        while pieces < max_pieces && !game.is_game_over() {
+            for action in bot.best_inputs(game) {
+                game.apply(action);
+            }
+            game.tick();
+            pieces += 1;
+        }
+
+        pieces
+    }
+
+    #[test]
+    fn test_lookahead() {
+        let bot = Bot {
+            lookahead: 1,
+            ..Bot::default()
+        };
+        let mut game = seeded_game();
+        assert_eq!(play(&bot, &mut game, 5), 5);
+        assert!(!game.is_game_over());
+    }
+
+    #[test]
+    fn test_play_headless() {
+        let bot = Bot::default();
+        let mut game = seeded_game();
+        let pieces = play(&bot, &mut game, 100);
+        assert_eq!(pieces, 100);
+        assert!(!game.is_game_over());
+        assert!(game.get_lines() > 0);
+    }
+}
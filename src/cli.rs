@@ -0,0 +1,196 @@
+//! Command-line options of the native window, to start a given game from a
+//! script or play back a reported bug.
+
+use crate::game::{GameOptions, MAX_GRID_HEIGHT, MAX_GRID_WIDTH, MAX_LEVEL, MIN_GRID_SIZE};
+use crate::settings::ThemeMode;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: tetris-slint [OPTIONS]
+
+Options:
+  --seed <N>           Seed of the random pieces and garbage
+  --mode <MODE>        Start a game at once: marathon, dig-race or puzzle:<N>
+  --level <N>          Starting level, from 1 to 20
+  --replay <FILE>      Play back a recorded game
+  --width <N>          Columns of the grid, from 4 to 32
+  --height <N>         Rows of the grid, from 4 to 40
+  --theme <THEME>      Colour theme: system, light or dark
+  --config <FILE>      Settings file to use instead of the default one
+  -h, --help           Print this help
+";
+
+/// The game started at launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartMode {
+    Marathon,
+    DigRace,
+    /// A puzzle of the built-in then user packs, numbered from 0.
+    Puzzle(usize),
+}
+
+/// The parsed command-line options.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Args {
+    pub seed: Option<u64>,
+    pub mode: Option<StartMode>,
+    pub level: Option<u32>,
+    pub replay: Option<PathBuf>,
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    pub theme: Option<ThemeMode>,
+    pub config: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses command-line options, as `--option value` or `--option=value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The arguments, without the program name.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Args, String>` - The options, or a message for the first
+    ///   invalid one.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if name == "-h" || name == "--help" {
+                parsed.help = true;
+                continue;
+            }
+            let value = inline
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("missing value for `{}`", name))?;
+            match name.as_str() {
+                "--seed" => parsed.seed = Some(number(&name, &value, 0, u64::MAX)?),
+                "--mode" => parsed.mode = Some(parse_mode(&value)?),
+                "--level" => parsed.level = Some(number(&name, &value, 1, MAX_LEVEL)?),
+                "--replay" => parsed.replay = Some(PathBuf::from(value)),
+                "--width" => {
+                    parsed.width = Some(number(&name, &value, MIN_GRID_SIZE, MAX_GRID_WIDTH)?)
+                }
+                "--height" => {
+                    parsed.height = Some(number(&name, &value, MIN_GRID_SIZE, MAX_GRID_HEIGHT)?)
+                }
+                "--theme" => {
+                    parsed.theme = Some(
+                        ThemeMode::from_name(&value)
+                            .ok_or_else(|| format!("unknown theme `{}`", value))?,
+                    )
+                }
+                "--config" => parsed.config = Some(PathBuf::from(value)),
+                _ => return Err(format!("unknown option `{}`", name)),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns the options of a new game: the given seed, or a random one
+    /// when none was given, and the given size and level.
+    pub fn game_options(&self) -> GameOptions {
+        let defaults = GameOptions::default();
+        GameOptions {
+            seed: self.seed.unwrap_or(defaults.seed),
+            width: self.width.unwrap_or(defaults.width),
+            height: self.height.unwrap_or(defaults.height),
+            level: self.level.unwrap_or(defaults.level),
+        }
+    }
+}
+
+/// Returns the options of the process, or exits with the usage on invalid
+/// options or `--help`. The web version has no command line.
+pub fn from_env() -> Args {
+    if cfg!(target_arch = "wasm32") {
+        return Args::default();
+    }
+    match Args::parse(std::env::args().skip(1)) {
+        Ok(args) if args.help => {
+            print!("{}", USAGE);
+            std::process::exit(0);
+        }
+        Ok(args) => args,
+        Err(error) => {
+            eprint!("error: {}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn number<T>(name: &str, value: &str, min: T, max: T) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    value
+        .parse::<T>()
+        .ok()
+        .filter(|n| *n >= min && *n <= max)
+        .ok_or_else(|| {
+            format!(
+                "`{}` expects a number from {} to {}, found `{}`",
+                name, min, max, value
+            )
+        })
+}
+
+fn parse_mode(value: &str) -> Result<StartMode, String> {
+    match value {
+        "marathon" => Ok(StartMode::Marathon),
+        "dig-race" => Ok(StartMode::DigRace),
+        _ => value
+            .strip_prefix("puzzle:")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .map(|n| StartMode::Puzzle(n - 1))
+            .ok_or_else(|| format!("unknown mode `{}`", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(&[
+            "--seed",
+            "42",
+            "--mode=puzzle:2",
+            "--level",
+            "5",
+            "--width=12",
+            "--theme",
+            "dark",
+        ])
+        .unwrap();
+        assert_eq!(args.seed, Some(42));
+        assert_eq!(args.mode, Some(StartMode::Puzzle(1)));
+        assert_eq!(args.theme, Some(ThemeMode::Dark));
+        let options = args.game_options();
+        assert_eq!((options.seed, options.width, options.level), (42, 12, 5));
+        assert_eq!(options.height, crate::game::Game::GRID_HEIGHT);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--level", "0"]).is_err());
+        assert!(parse(&["--width", "100"]).is_err());
+        assert!(parse(&["--mode", "zen"]).is_err());
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+        assert!(parse(&["--help"]).unwrap().help);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    bot::Bot,
+    controller::game_controller,
+    game::{Action, Game},
+    skin::Skin,
+    ui::*,
+};
+use slint::*;
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
+
+/// Time between two bot inputs.
+const STEP: Duration = Duration::from_millis(100);
+/// Number of idle steps on the title screen before the demo starts.
+const IDLE_STEPS: u32 = 100;
+
+struct Demo {
+    game: Game,
+    bot: Bot,
+    inputs: VecDeque<Action>,
+    idle_steps: u32,
+}
+
+/// Starts the attract mode: after the title screen has been idle for a while,
+/// a bot plays a game in the main grid until a key is pressed.
+pub fn setup(
+    window: &AppWindow,
+    view: Rc<RefCell<game_controller::BoardView>>,
+    skin: Rc<RefCell<Skin>>,
+) -> Timer {
+    let demo = Rc::new(RefCell::new(Demo {
+        game: Game::new(),
+        bot: Bot::default(),
+        inputs: VecDeque::new(),
+        idle_steps: 0,
+    }));
+
+    let demo_timer = Timer::default();
+    demo_timer.start(TimerMode::Repeated, STEP, {
+        let weak_window = window.as_weak();
+
+        move || {
+            let window = weak_window.unwrap();
+            let game_adapter = window.global::<GameAdapter>();
+            let mut demo = demo.borrow_mut();
+            if !game_adapter.get_demo() {
+                demo.idle_steps = if game_adapter.get_playing() {
+                    0
+                } else {
+                    demo.idle_steps + 1
+                };
+                if demo.idle_steps < IDLE_STEPS {
+                    return;
+                }
+                demo.game = Game::new();
+                demo.inputs.clear();
+                game_adapter.set_summary_show(false);
+                game_adapter.set_round_over_show(false);
+                game_adapter.set_versus(false);
+                game_adapter.set_is_multiplayer(false);
+                game_adapter.set_demo(true);
+            }
+            demo.idle_steps = 0;
+            step(&mut demo);
+            game_controller::update_ui(
+                &game_adapter,
+                &mut view.borrow_mut(),
+                &mut demo.game,
+                &skin.borrow(),
+            );
+            game_controller::show_events(&window, demo.game.take_events());
+        }
+    });
+
+    demo_timer
+}
+
+fn step(demo: &mut Demo) {
+    if demo.game.is_game_over() {
+        demo.game = Game::new();
+        demo.inputs.clear();
+    }
+    if demo.inputs.is_empty() {
+        demo.inputs = demo.bot.best_inputs(&demo.game).into();
+    }
+    match demo.inputs.pop_front() {
+        Some(Action::HardDrop) => {
+            demo.game.apply(Action::HardDrop);
+            demo.game.tick();
+        }
+        Some(action) => {
+            demo.game.apply(action);
+        }
+        None => demo.game.tick(),
+    }
+}
@@ -1,112 +1,496 @@
-use crate::{game::Game, pieces, ui::*};
+use crate::{
+    game::{self, Event, Game, GameMode},
+    pieces,
+    replay::Replay,
+    skin::Skin,
+    storage,
+    ui::*,
+    utils::Vector2,
+};
 use slint::*;
 use std::{cell::RefCell, rc::Rc, time::Duration};
 
-pub fn setup(window: &AppWindow, game: Rc<RefCell<Game>>) -> Timer {
+const FLASH_DURATION: Duration = Duration::from_millis(300);
+/// How long an effect stays in the model, longer than any of its animations.
+const EFFECT_LIFETIME: Duration = Duration::from_millis(500);
+
+pub fn setup(
+    window: &AppWindow,
+    game: Rc<RefCell<Game>>,
+    view: Rc<RefCell<BoardView>>,
+    skin: Rc<RefCell<Skin>>,
+) -> Timer {
     window.global::<GameAdapter>().set_grid_size(Size {
         height: Game::GRID_HEIGHT.into(),
         width: Game::GRID_WIDTH.into(),
     });
+    window
+        .global::<GameAdapter>()
+        .set_grid(view.borrow().grid_model());
+    window
+        .global::<GameAdapter>()
+        .set_stats(view.borrow().stats_model());
+    window
+        .global::<GameAdapter>()
+        .set_effects(ModelRc::new(VecModel::<SEffect>::default()));
 
     let update_timer = Timer::default();
-    update_timer.start(TimerMode::Repeated, Duration::from_millis(30), {
+    update_timer.start(TimerMode::Repeated, game::FRAME, {
         let weak_window = window.as_weak();
+        let mut last_faults = 0;
 
         move || {
             let window = weak_window.unwrap();
             let game_adapter = window.global::<GameAdapter>();
-            if game.borrow().is_game_over() {
+            // Local versus runs its own games
+            if game_adapter.get_versus() {
+                return;
+            }
+            if game_adapter.get_playing() {
+                game.borrow_mut().update();
+                update_ui(
+                    &game_adapter,
+                    &mut view.borrow_mut(),
+                    &mut game.borrow_mut(),
+                    &skin.borrow(),
+                );
+                show_events(&window, game.borrow_mut().take_events());
+
+                // Flash the grid on finesse faults while training
+                let faults = game.borrow().get_finesse().faults;
+                if faults > last_faults && game_adapter.get_finesse_training() != 0 {
+                    game_adapter.set_fault_flash(true);
+                    let weak_window = weak_window.clone();
+                    Timer::single_shot(FLASH_DURATION, move || {
+                        if let Some(window) = weak_window.upgrade() {
+                            window.global::<GameAdapter>().set_fault_flash(false);
+                        }
+                    });
+                }
+                last_faults = faults;
+            }
+            if game.borrow().is_game_over() && game_adapter.get_playing() {
+                let game = game.borrow();
+                if !game.is_replaying() {
+                    if let Some(Err(error)) = Replay::record(&game).map(|replay| replay.save_last())
+                    {
+                        storage::report(&slint::format!("Could not save the replay: {error}"));
+                    }
+                }
+                game_adapter.set_message(result_message(&game));
                 game_adapter.set_game_over(true);
+                game_adapter.set_summary_show(true);
                 game_adapter.set_playing(false);
             }
-            update_ui(&game_adapter, &game.borrow());
         }
     });
 
     update_timer
 }
 
-fn update_ui(game_grid_adapter: &GameAdapter, game: &Game) {
-    if !game_grid_adapter.get_playing() {
+/// The grid and piece previews shown for a game. The models are kept between
+/// frames so that only the cells that changed are updated.
+pub struct BoardView {
+    grid: Rc<VecModel<ModelRc<SBlock>>>,
+    rows: Vec<Rc<VecModel<SBlock>>>,
+    /// What each cell shows, current piece included, and whether it is part
+    /// of the ghost piece.
+    shown: Vec<Vec<(Option<pieces::PieceKind>, bool)>>,
+    current: Vec<Vector2>,
+    ghost: Vec<Vector2>,
+    /// Id of the game drawn last, to redraw every cell for another game.
+    game: u64,
+    skin: String,
+    show_ghost: bool,
+    previews: Option<(Vec<pieces::Piece>, Option<pieces::Piece>)>,
+    stats: Rc<VecModel<StatLine>>,
+}
+
+impl BoardView {
+    pub fn new() -> BoardView {
+        let mut view = BoardView {
+            grid: Rc::new(VecModel::default()),
+            rows: Vec::new(),
+            shown: Vec::new(),
+            current: Vec::new(),
+            ghost: Vec::new(),
+            game: 0,
+            skin: String::new(),
+            show_ghost: false,
+            previews: None,
+            stats: Rc::new(VecModel::default()),
+        };
+        view.resize(Game::GRID_WIDTH, Game::GRID_HEIGHT);
+        view
+    }
+
+    /// Replaces the rows of the grid model with empty rows of another size.
+    fn resize(&mut self, width: u16, height: u16) {
+        let (width, height) = (width as usize, height as usize);
+        self.rows = (0..height)
+            .map(|_| Rc::new(VecModel::from(vec![SBlock::default(); width])))
+            .collect();
+        self.grid.set_vec(
+            self.rows
+                .iter()
+                .map(|row| ModelRc::from(row.clone()))
+                .collect::<Vec<_>>(),
+        );
+        self.shown = vec![vec![(None, false); width]; height];
+    }
+
+    /// Returns the grid model, to be shown by a `GridBlockDisplay`.
+    pub fn grid_model(&self) -> ModelRc<ModelRc<SBlock>> {
+        self.grid.clone().into()
+    }
+
+    /// Returns the statistics model, to be shown by a `StatsPanel`.
+    pub fn stats_model(&self) -> ModelRc<StatLine> {
+        self.stats.clone().into()
+    }
+
+    /// Updates the statistics lines whose value changed.
+    fn draw_stats(&mut self, game: &Game) {
+        let lines = stat_lines(game);
+        if lines.len() != self.stats.row_count() {
+            self.stats.set_vec(lines);
+            return;
+        }
+        for (i, line) in lines.into_iter().enumerate() {
+            if self.stats.row_data(i).as_ref() != Some(&line) {
+                self.stats.set_row_data(i, line);
+            }
+        }
+    }
+
+    /// Updates the cells that changed since the last frame: the cells the
+    /// engine reports as dirty and the old and new cells of the current and
+    /// ghost pieces. Every cell is updated when another game or skin is drawn.
+    pub fn draw_grid(&mut self, game: &mut Game, skin: &Skin, show_ghost: bool) {
+        let id = game.id();
+        let full = id != self.game || skin.name != self.skin || show_ghost != self.show_ghost;
+        self.game = id;
+        let (width, height) = (game.width(), game.height());
+        if full {
+            self.skin = skin.name.clone();
+            self.show_ghost = show_ghost;
+            if self.shown.len() != height as usize || self.shown[0].len() != width as usize {
+                self.resize(width, height);
+            }
+        }
+
+        let dirty = game.take_dirty_cells();
+        // The last piece is already part of the grid until the next spawns
+        let in_play = !game.is_between_pieces();
+        let current = if in_play {
+            game.get_current().cells()
+        } else {
+            Vec::new()
+        };
+        let current_kind = game.get_current().piece.kind;
+        let ghost = if show_ghost && in_play {
+            game.get_ghost().cells()
+        } else {
+            Vec::new()
+        };
+        let cells: Vec<Vector2> = if full {
+            (0..height as i16)
+                .flat_map(|y| (0..width as i16).map(move |x| Vector2::new(x, y)))
+                .collect()
+        } else {
+            dirty
+                .into_iter()
+                .map(|(x, y)| Vector2::new(x as i16, y as i16))
+                .chain(self.current.iter().copied())
+                .chain(current.iter().copied())
+                .chain(self.ghost.iter().copied())
+                .chain(ghost.iter().copied())
+                .collect()
+        };
+
+        let grid = game.get_grid();
+        for cell in cells {
+            if cell.x < 0 || cell.y < 0 || cell.x >= width as i16 || cell.y >= height as i16 {
+                continue;
+            }
+            let (x, y) = (cell.x as usize, cell.y as usize);
+            let shown = if current.contains(&cell) {
+                (Some(current_kind), false)
+            } else if grid[y][x].is_none() && ghost.contains(&cell) {
+                (Some(current_kind), true)
+            } else {
+                (grid[y][x], false)
+            };
+            if full || self.shown[y][x] != shown {
+                self.shown[y][x] = shown;
+                let (kind, ghost) = shown;
+                self.rows[y].set_row_data(
+                    x,
+                    SBlock {
+                        ghost,
+                        ..col2block(skin, kind)
+                    },
+                );
+            }
+        }
+        self.current = current;
+        self.ghost = ghost;
+        if full {
+            self.previews = None;
+        }
+    }
+
+    /// Updates the next and held pieces if they changed.
+    fn draw_previews(
+        &mut self,
+        game_grid_adapter: &GameAdapter,
+        game: &Game,
+        skin: &Skin,
+        count: usize,
+    ) {
+        let previews = (game.get_previews(count), *game.get_held());
+        if self.previews.as_ref() == Some(&previews) {
+            return;
+        }
+        let next: Vec<SPiece> = previews.0.iter().map(|p| preview(Some(p), skin)).collect();
+        game_grid_adapter.set_next_pieces(ModelRc::new(VecModel::from(next)));
+        game_grid_adapter.set_held_piece(preview(previews.1.as_ref(), skin));
+        self.previews = Some(previews);
+    }
+}
+
+impl Default for BoardView {
+    fn default() -> Self {
+        BoardView::new()
+    }
+}
+
+/// Copies the state of the game into the adapter.
+pub fn update_ui(
+    game_grid_adapter: &GameAdapter,
+    view: &mut BoardView,
+    game: &mut Game,
+    skin: &Skin,
+) {
+    let size = Size {
+        width: game.width().into(),
+        height: game.height().into(),
+    };
+    if game_grid_adapter.get_grid_size() != size {
+        game_grid_adapter.set_grid_size(size);
+    }
+    view.draw_grid(game, skin, game_grid_adapter.get_ghost());
+    let count = game_grid_adapter.get_preview_count().max(1) as usize;
+    view.draw_previews(game_grid_adapter, game, skin, count);
+
+    // Score
+    game_grid_adapter.set_score(game.get_score() as i32);
+    game_grid_adapter.set_garbage_remaining(game.get_garbage_remaining() as i32);
+
+    // Finesse
+    let finesse = game.get_finesse();
+    game_grid_adapter.set_finesse(slint::format!(
+        "FINESSE {:.0}%  FAULTS {}",
+        finesse.percentage(),
+        finesse.faults
+    ));
+
+    // Statistics
+    view.draw_stats(game);
+
+    // Objective
+    if let GameMode::Puzzle { objective } = game.get_mode() {
+        game_grid_adapter.set_objective(slint::format!("{}", objective));
+    } else {
+        game_grid_adapter.set_objective(SharedString::new());
+    }
+}
+
+/// Adds the animations of game events to the grid. Each effect is removed
+/// once its animation is over.
+pub fn show_events(window: &AppWindow, events: Vec<Event>) {
+    let width = window.global::<GameAdapter>().get_grid_size().width;
+    let effects = window.global::<GameAdapter>().get_effects();
+    let Some(effects) = effects.as_any().downcast_ref::<VecModel<SEffect>>() else {
         return;
     };
-    // Grid
-    let grid = game.get_grid();
-    let vec = VecModel::<ModelRc<slint::Color>>::default();
-    for i in 0..Game::GRID_HEIGHT {
-        let row = VecModel::<Color>::default();
-        for j in 0..Game::GRID_WIDTH {
-            row.insert(j.into(), col2col(grid[i as usize][j as usize]));
-        }
-        vec.insert(i.into(), Rc::new(row).clone().into());
-    }
-    // Current piece
-    let current = game.get_current();
-    for cell in current.get_shape() {
-        let x = current.x + cell.0 as i16;
-        let y = current.y + cell.1 as i16;
-
-        let row = vec.row_data(y as usize);
-        if row.is_some() {
-            row.unwrap()
-                .set_row_data(x as usize, col2col(Some(current.piece.color)));
-        }
-    }
-    game_grid_adapter.set_grid(Rc::new(vec).clone().into());
-
-    // Next piece
-    let next = game.get_next();
-    game_grid_adapter.set_next_piece(SPiece {
-        blocks: piece_to_model(next),
-        is_I: next.color == pieces::Color::CYAN,
-        is_O: next.color == pieces::Color::YELLOW,
-    });
+    let effect = |kind, x, y, width, height| SEffect {
+        kind,
+        x: x as i32,
+        y: y as i32,
+        width,
+        height: height as i32,
+    };
+    let count = effects.row_count();
+    for event in events {
+        match event {
+            Event::LinesCleared { rows } => {
+                for y in rows {
+                    effects.push(effect(0, 0, y as i16, width, 1));
+                }
+            }
+            Event::Locked { cells } => {
+                for cell in cells.into_iter().filter(|cell| cell.y >= 0) {
+                    effects.push(effect(1, cell.x, cell.y, 1, 1));
+                }
+            }
+            Event::HardDrop { cells, distance } => {
+                // One trail per column, above the highest cell of the piece
+                let mut columns: Vec<(i16, i16)> = Vec::new();
+                for cell in cells {
+                    match columns.iter_mut().find(|(column, _)| *column == cell.x) {
+                        Some((_, top)) => *top = (*top).min(cell.y),
+                        None => columns.push((cell.x, cell.y)),
+                    }
+                }
+                for (x, top) in columns {
+                    let start = (top - distance as i16).max(0);
+                    if top > start {
+                        effects.push(effect(2, x, start, 1, top - start));
+                    }
+                }
+            }
+        }
+    }
 
-    // Held piece
-    if let Some(held) = game.get_held() {
-        game_grid_adapter.set_held_piece(SPiece {
-            blocks: piece_to_model(&held),
-            is_I: held.color == pieces::Color::CYAN,
-            is_O: held.color == pieces::Color::YELLOW,
+    let added = effects.row_count() - count;
+    if added > 0 {
+        let weak_window = window.as_weak();
+        Timer::single_shot(EFFECT_LIFETIME, move || {
+            let Some(window) = weak_window.upgrade() else {
+                return;
+            };
+            let effects = window.global::<GameAdapter>().get_effects();
+            if let Some(effects) = effects.as_any().downcast_ref::<VecModel<SEffect>>() {
+                for _ in 0..added.min(effects.row_count()) {
+                    effects.remove(0);
+                }
+            }
         });
     }
+}
 
-    // Score
-    game_grid_adapter.set_score(game.get_score() as i32);
+fn stat_lines(game: &Game) -> Vec<StatLine> {
+    let stats = game.get_stats();
+    let elapsed = game.get_elapsed();
+    let line = |label: &str, value: SharedString| StatLine {
+        label: label.into(),
+        value,
+    };
+    let mut lines = vec![
+        line(
+            "TIME",
+            slint::format!("{}:{:02}", elapsed.as_secs() / 60, elapsed.as_secs() % 60),
+        ),
+        line("PIECES", slint::format!("{}", stats.pieces)),
+        line("PPS", slint::format!("{:.2}", stats.pps(elapsed))),
+        line("KPP", slint::format!("{:.2}", stats.kpp())),
+        line("APM", slint::format!("{:.1}", stats.apm(elapsed))),
+        line("HOLDS", slint::format!("{}", stats.holds)),
+        line("LINES", slint::format!("{}", game.get_lines())),
+        line("LEVEL", slint::format!("{}", game.get_level())),
+    ];
+    for (label, count) in ["SINGLE", "DOUBLE", "TRIPLE", "TETRIS"]
+        .iter()
+        .zip(stats.clears)
+    {
+        lines.push(line(label, slint::format!("{}", count)));
+    }
+    for (label, count) in ["T-SPIN SINGLE", "T-SPIN DOUBLE", "T-SPIN TRIPLE"]
+        .iter()
+        .zip(stats.tspin_clears)
+    {
+        lines.push(line(label, slint::format!("{}", count)));
+    }
+    lines.push(line(
+        "PERFECT CLEAR",
+        slint::format!("{}", stats.perfect_clears),
+    ));
+    for (label, count) in ["I", "J", "L", "O", "S", "T", "Z"]
+        .iter()
+        .zip(stats.piece_counts)
+    {
+        lines.push(line(label, slint::format!("{}", count)));
+    }
+
+    lines
 }
 
-fn piece_to_model(piece: &pieces::Piece) -> ModelRc<ModelRc<Color>> {
-    let piece_shape = piece.get_shape(0);
-    let vec = VecModel::<ModelRc<slint::Color>>::default();
-    for i in 0..4 {
-        let row = VecModel::<slint::Color>::from_slice(&[
-            col2col(None),
-            col2col(None),
-            col2col(None),
-            col2col(None),
-        ]);
-        vec.insert(i, row);
+fn result_message(game: &Game) -> SharedString {
+    let time = game.get_finished_in().unwrap_or_default().as_secs_f32();
+    match (game.get_mode(), game.is_completed()) {
+        (GameMode::DigRace { .. }, true) => slint::format!("Cleared in {:.2}s", time),
+        (GameMode::Puzzle { .. }, true) => slint::format!("Solved in {:.2}s", time),
+        (GameMode::Puzzle { .. }, false) => "Puzzle failed".into(),
+        _ if game.is_replaying() => "Replay over".into(),
+        _ => SharedString::new(),
     }
-    for cell in piece_shape {
-        let x = cell.0 as usize;
-        let y = cell.1 as usize;
+}
 
-        let row = vec.row_data(y);
-        row.unwrap().set_row_data(x, col2col(Some(piece.color)));
+/// Returns a piece as shown by a `PieceDisplay`, empty for None.
+pub fn preview(piece: Option<&pieces::Piece>, skin: &Skin) -> SPiece {
+    match piece {
+        Some(piece) => {
+            let (_, size) = piece.bounding_box(0);
+            SPiece {
+                blocks: piece_to_model(piece, skin),
+                width: size.x as i32,
+                height: size.y as i32,
+            }
+        }
+        None => SPiece::default(),
     }
+}
 
-    Rc::new(vec).clone().into()
+/// Returns the blocks of the piece in its spawn rotation, cropped to the
+/// cells it covers.
+fn piece_to_model(piece: &pieces::Piece, skin: &Skin) -> ModelRc<ModelRc<SBlock>> {
+    let (corner, size) = piece.bounding_box(0);
+    let mut rows = vec![vec![col2block(skin, None); size.x as usize]; size.y as usize];
+    for cell in piece.get_shape(0).iter().map(|cell| cell.sub(corner)) {
+        rows[cell.y as usize][cell.x as usize] = col2block(skin, Some(piece.kind));
+    }
+
+    let rows: Vec<ModelRc<SBlock>> = rows
+        .into_iter()
+        .map(|row| ModelRc::new(VecModel::from(row)))
+        .collect();
+    ModelRc::new(VecModel::from(rows))
 }
 
-fn col2col(color: Option<pieces::Color>) -> slint::Color {
-    match color {
-        Some(pieces::Color::CYAN) => slint::Color::from_rgb_u8(82, 177, 252),
-        Some(pieces::Color::BLUE) => slint::Color::from_rgb_u8(60, 118, 181),
-        Some(pieces::Color::ORANGE) => slint::Color::from_rgb_u8(255, 92, 27),
-        Some(pieces::Color::YELLOW) => slint::Color::from_rgb_u8(251, 206, 5),
-        Some(pieces::Color::GREEN) => slint::Color::from_rgb_u8(67, 213, 97),
-        Some(pieces::Color::PURPLE) => slint::Color::from_rgb_u8(164, 105, 184),
-        Some(pieces::Color::RED) => slint::Color::from_rgb_u8(255, 1, 39),
+/// Applies a skin to the blocks and shows it in the settings preview.
+pub fn set_skin(window: &AppWindow, skin: &Skin) {
+    let block_skin = window.global::<BlockSkin>();
+    block_skin.set_style(skin.style.index());
+    block_skin.set_radius(skin.radius);
+
+    let preview: Vec<SBlock> = pieces::PieceKind::COLORED
+        .into_iter()
+        .map(|kind| col2block(skin, Some(kind)))
+        .collect();
+    window
+        .global::<GameAdapter>()
+        .set_skin_preview(ModelRc::new(VecModel::from(preview)));
+}
+
+fn col2block(skin: &Skin, kind: Option<pieces::PieceKind>) -> SBlock {
+    SBlock {
+        color: col2col(skin, kind),
+        kind: match kind {
+            None => 0,
+            Some(pieces::PieceKind::Custom(number)) => 9 + number as i32,
+            Some(kind) => kind.color_index() as i32 + 1,
+        },
+        ghost: false,
+    }
+}
+
+fn col2col(skin: &Skin, kind: Option<pieces::PieceKind>) -> slint::Color {
+    match kind {
+        Some(kind) => {
+            let (r, g, b) = skin.rgb(kind);
+            slint::Color::from_rgb_u8(r, g, b)
+        }
         None => slint::Color::from_argb_u8(0, 0, 0, 0),
     }
 }
@@ -116,34 +500,176 @@ mod tests {
 
     #[test]
     fn test_col2col() {
+        let skin = Skin::default();
         assert_eq!(
-            col2col(Some(pieces::Color::CYAN)),
+            col2col(&skin, Some(pieces::PieceKind::I)),
             slint::Color::from_rgb_u8(82, 177, 252)
         );
         assert_eq!(
-            col2col(Some(pieces::Color::BLUE)),
+            col2col(&skin, Some(pieces::PieceKind::J)),
             slint::Color::from_rgb_u8(60, 118, 181)
         );
         assert_eq!(
-            col2col(Some(pieces::Color::ORANGE)),
+            col2col(&skin, Some(pieces::PieceKind::L)),
             slint::Color::from_rgb_u8(255, 92, 27)
         );
         assert_eq!(
-            col2col(Some(pieces::Color::YELLOW)),
+            col2col(&skin, Some(pieces::PieceKind::O)),
             slint::Color::from_rgb_u8(251, 206, 5)
         );
         assert_eq!(
-            col2col(Some(pieces::Color::GREEN)),
+            col2col(&skin, Some(pieces::PieceKind::S)),
             slint::Color::from_rgb_u8(67, 213, 97)
         );
         assert_eq!(
-            col2col(Some(pieces::Color::PURPLE)),
+            col2col(&skin, Some(pieces::PieceKind::T)),
             slint::Color::from_rgb_u8(164, 105, 184)
         );
         assert_eq!(
-            col2col(Some(pieces::Color::RED)),
+            col2col(&skin, Some(pieces::PieceKind::Z)),
             slint::Color::from_rgb_u8(255, 1, 39)
         );
-        assert_eq!(col2col(None), slint::Color::from_argb_u8(0, 0, 0, 0));
+        assert_eq!(
+            col2col(&skin, Some(pieces::PieceKind::Garbage)),
+            slint::Color::from_rgb_u8(130, 130, 130)
+        );
+        assert_eq!(col2col(&skin, None), slint::Color::from_argb_u8(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_board_view() {
+        let skin = Skin::default();
+        let mut view = BoardView::new();
+        let mut game = Game::new();
+        view.draw_grid(&mut game, &skin, false);
+        let cell = |view: &BoardView, position: Vector2| {
+            view.grid_model()
+                .row_data(position.y as usize)
+                .and_then(|row| row.row_data(position.x as usize))
+                .unwrap()
+        };
+        let start = game.get_current().cells();
+        let kind = game.get_current().piece.kind;
+        for &position in start.iter().filter(|position| position.y >= 0) {
+            assert_eq!(cell(&view, position), col2block(&skin, Some(kind)));
+        }
+
+        game.apply(crate::game::Action::HardDrop);
+        view.draw_grid(&mut game, &skin, false);
+        for position in game.get_current().cells() {
+            assert_eq!(cell(&view, position), col2block(&skin, Some(kind)));
+        }
+        for &position in start.iter().filter(|position| position.y >= 0) {
+            assert_eq!(cell(&view, position), col2block(&skin, None));
+        }
+    }
+
+    #[test]
+    fn test_board_view_new_game() {
+        let skin = Skin::default();
+        let mut view = BoardView::new();
+        let mut game = Game::new();
+        let kind = game.get_current().piece.kind;
+        game.apply(crate::game::Action::HardDrop);
+        let dropped = game.get_current().cells();
+        game.tick();
+        view.draw_grid(&mut game, &skin, false);
+        let cell = |view: &BoardView, position: &Vector2| {
+            view.grid_model()
+                .row_data(position.y as usize)
+                .and_then(|row| row.row_data(position.x as usize))
+                .unwrap()
+        };
+        for position in &dropped {
+            assert_eq!(cell(&view, position), col2block(&skin, Some(kind)));
+        }
+
+        // Another game in the same place, with no cell reported as dirty
+        game = Game::new();
+        game.take_dirty_cells();
+        view.draw_grid(&mut game, &skin, false);
+        for position in &dropped {
+            assert_eq!(cell(&view, position), col2block(&skin, None));
+        }
+    }
+
+    #[test]
+    fn test_board_view_stats() {
+        let mut view = BoardView::new();
+        let model = view.stats_model();
+        let mut game = Game::new();
+        view.draw_stats(&game);
+        let pieces = |model: &ModelRc<StatLine>| {
+            model
+                .iter()
+                .find(|line| line.label == "PIECES")
+                .map(|line| line.value)
+        };
+        assert_eq!(model.row_count(), stat_lines(&game).len());
+        assert_eq!(pieces(&model), Some("0".into()));
+
+        // The same model is updated in place
+        game.press(crate::game::Action::HardDrop);
+        view.draw_stats(&game);
+        assert_eq!(pieces(&model), Some("1".into()));
+    }
+
+    #[test]
+    fn test_board_view_ghost() {
+        let skin = Skin::default();
+        let mut view = BoardView::new();
+        let mut game = Game::new();
+        view.draw_grid(&mut game, &skin, true);
+        let current = game.get_current().cells();
+        for cell in game.get_ghost().cells() {
+            let block = view.grid_model().row_data(cell.y as usize).unwrap();
+            let block = block.row_data(cell.x as usize).unwrap();
+            assert_eq!(block.ghost, !current.contains(&cell));
+        }
+    }
+
+    #[test]
+    fn test_board_view_resize() {
+        let skin = Skin::default();
+        let mut view = BoardView::new();
+        let mut game = Game::with_options(game::GameOptions {
+            width: 6,
+            height: 8,
+            ..Default::default()
+        });
+        view.draw_grid(&mut game, &skin, false);
+        assert_eq!(view.grid_model().row_count(), 8);
+        assert_eq!(view.grid_model().row_data(0).unwrap().row_count(), 6);
+    }
+
+    #[test]
+    fn test_preview_size() {
+        let skin = Skin::default();
+        for (piece, width, height) in [
+            (&pieces::BLOCK_I, 4, 1),
+            (&pieces::BLOCK_O, 2, 2),
+            (&pieces::BLOCK_T, 3, 2),
+        ] {
+            let shown = preview(Some(piece), &skin);
+            assert_eq!((shown.width, shown.height), (width, height));
+            assert_eq!(shown.blocks.row_count(), height as usize);
+            let first = shown.blocks.row_data(0).unwrap();
+            assert_eq!(first.row_count(), width as usize);
+        }
+        let top = preview(Some(&pieces::BLOCK_I), &skin).blocks.row_data(0);
+        assert!(top.unwrap().iter().all(|block| block.kind == 1));
+    }
+
+    #[test]
+    fn test_col2block() {
+        let skin = Skin::default();
+        assert_eq!(col2block(&skin, None).kind, 0);
+        assert_eq!(col2block(&skin, Some(pieces::PieceKind::I)).kind, 1);
+        assert_eq!(col2block(&skin, Some(pieces::PieceKind::Z)).kind, 7);
+        assert_eq!(col2block(&skin, Some(pieces::PieceKind::Garbage)).kind, 8);
+        assert_eq!(
+            col2block(&skin, Some(pieces::PieceKind::Custom(2))).kind,
+            11
+        );
     }
 }
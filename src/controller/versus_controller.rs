@@ -0,0 +1,109 @@
+use crate::{
+    controller::game_controller::{self, BoardView},
+    game::{self, Game},
+    pieces::Piece,
+    skin::Skin,
+    ui::*,
+    versus::{Outcome, Versus},
+};
+use slint::*;
+use std::{cell::RefCell, rc::Rc};
+
+/// Runs versus: player 1 on the main grid and player 2, or the computer, on
+/// the opponent grid, until one of them tops out and the round end screen is
+/// shown.
+pub fn setup(
+    window: &AppWindow,
+    versus: Rc<RefCell<Versus>>,
+    view: Rc<RefCell<BoardView>>,
+    skin: Rc<RefCell<Skin>>,
+) -> Timer {
+    let mut opponent_view = BoardView::new();
+    window
+        .global::<GameAdapter>()
+        .set_opponent_grid(opponent_view.grid_model());
+
+    let update_timer = Timer::default();
+    update_timer.start(TimerMode::Repeated, game::FRAME, {
+        let weak_window = window.as_weak();
+        let mut opponent_previews = None;
+
+        move || {
+            let window = weak_window.unwrap();
+            let game_adapter = window.global::<GameAdapter>();
+            if !game_adapter.get_versus() || !game_adapter.get_playing() {
+                return;
+            }
+            let mut versus = versus.borrow_mut();
+            let skin = skin.borrow();
+            versus.update();
+            game_controller::update_ui(
+                &game_adapter,
+                &mut view.borrow_mut(),
+                versus.game_mut(0),
+                &skin,
+            );
+            game_controller::show_events(&window, versus.game_mut(0).take_events());
+            draw_opponent(
+                &game_adapter,
+                &mut opponent_view,
+                &mut opponent_previews,
+                versus.game_mut(1),
+                &skin,
+            );
+
+            if let Some(outcome) = versus.outcome() {
+                let names = if versus.has_cpu() {
+                    ["You", "CPU"]
+                } else {
+                    ["Player 1", "Player 2"]
+                };
+                let message = match outcome {
+                    Outcome::Winner(0) if versus.has_cpu() => "You win".into(),
+                    Outcome::Winner(player) => slint::format!("{} wins", names[player]),
+                    Outcome::Draw => "Draw".into(),
+                };
+                let wins = versus.get_wins();
+                game_adapter.set_message(message);
+                game_adapter.set_round_tally(slint::format!(
+                    "{}  {} - {}  {}",
+                    names[0],
+                    wins[0],
+                    wins[1],
+                    names[1]
+                ));
+                game_adapter.set_game_over(true);
+                game_adapter.set_round_over_show(true);
+                game_adapter.set_playing(false);
+            }
+        }
+    });
+
+    update_timer
+}
+
+/// Copies the game of player 2 into the opponent grid, score and previews.
+fn draw_opponent(
+    game_adapter: &GameAdapter,
+    view: &mut BoardView,
+    previews: &mut Option<(Option<Piece>, Option<Piece>, String)>,
+    game: &mut Game,
+    skin: &Skin,
+) {
+    // Only the main grid is animated
+    game.take_events();
+    view.draw_grid(game, skin, game_adapter.get_ghost());
+    game_adapter.set_opponent_score(game.get_score() as i32);
+
+    // Updated when the pieces or the skin changed
+    let shown = (
+        game.get_next().copied(),
+        *game.get_held(),
+        skin.name.clone(),
+    );
+    if previews.as_ref() != Some(&shown) {
+        game_adapter.set_opponent_next_piece(game_controller::preview(shown.0.as_ref(), skin));
+        game_adapter.set_opponent_held_piece(game_controller::preview(shown.1.as_ref(), skin));
+        *previews = Some(shown);
+    }
+}
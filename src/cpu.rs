@@ -0,0 +1,220 @@
+//! A computer opponent for versus: the bot playing in real time, at the
+//! speed and with the mistakes of a difficulty.
+
+use crate::bot::Bot;
+use crate::game::{Action, Game};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// Frames per second of the game.
+const FPS: f32 = 60.0;
+/// Frames between two inputs of a piece.
+const INPUT_FRAMES: u32 = 2;
+/// Inputs a piece takes on average, to leave their frames out of the time
+/// spent thinking.
+const AVERAGE_INPUTS: u32 = 4;
+
+/// How well the computer plays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Difficulty {
+    /// Pieces placed per second.
+    pub pps: f32,
+    /// Chance, between 0 and 1, that a piece goes to a random place instead
+    /// of the best one.
+    pub mistake_rate: f32,
+    /// Number of next pieces considered when placing a piece.
+    pub lookahead: usize,
+}
+
+impl Difficulty {
+    pub const EASY: Difficulty = Difficulty {
+        pps: 0.8,
+        mistake_rate: 0.25,
+        lookahead: 0,
+    };
+    pub const MEDIUM: Difficulty = Difficulty {
+        pps: 1.5,
+        mistake_rate: 0.08,
+        lookahead: 0,
+    };
+    pub const HARD: Difficulty = Difficulty {
+        pps: 2.5,
+        mistake_rate: 0.0,
+        lookahead: 1,
+    };
+
+    /// Returns the difficulty for the index used by the window.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - 0 for easy, 1 for medium, 2 for hard.
+    ///
+    /// # Returns
+    ///
+    /// * `Difficulty` - The difficulty, medium for an unknown index.
+    pub fn from_index(index: i32) -> Difficulty {
+        match index {
+            0 => Difficulty::EASY,
+            2 => Difficulty::HARD,
+            _ => Difficulty::MEDIUM,
+        }
+    }
+}
+
+/// The computer playing a game, one frame at a time.
+pub struct CpuPlayer {
+    bot: Bot,
+    difficulty: Difficulty,
+    rng: StdRng,
+    /// Pieces locked in the game when the current piece was seen first.
+    piece: Option<u32>,
+    /// Inputs left for the current piece, or None before it is planned.
+    inputs: Option<VecDeque<Action>>,
+    /// Frames to wait before the next input.
+    wait: u32,
+}
+
+impl CpuPlayer {
+    /// Creates a computer player.
+    ///
+    /// # Arguments
+    ///
+    /// * `difficulty` - How well it plays.
+    /// * `seed` - Seed of its mistakes.
+    ///
+    /// # Returns
+    ///
+    /// * `CpuPlayer` - A new computer player.
+    pub fn new(difficulty: Difficulty, seed: u64) -> CpuPlayer {
+        CpuPlayer {
+            bot: Bot {
+                lookahead: difficulty.lookahead,
+                ..Bot::default()
+            },
+            difficulty,
+            rng: StdRng::seed_from_u64(seed),
+            piece: None,
+            inputs: None,
+            wait: 0,
+        }
+    }
+
+    /// Forgets the piece being placed, before a new game.
+    pub fn reset(&mut self) {
+        self.piece = None;
+        self.inputs = None;
+        self.wait = 0;
+    }
+
+    /// Plays one frame: waits while thinking about a new piece, then gives
+    /// its inputs a few frames apart and locks it.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - The game to play, run one frame per call.
+    pub fn act(&mut self, game: &mut Game) {
+        if game.is_game_over() || game.is_between_pieces() {
+            return;
+        }
+        // A new piece, or the last one locked before its inputs were given
+        let piece = game.get_stats().pieces;
+        if self.piece != Some(piece) {
+            self.piece = Some(piece);
+            self.inputs = None;
+            self.wait = self.think_frames();
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+
+        let inputs = match &mut self.inputs {
+            Some(inputs) => inputs,
+            None => {
+                let planned = self.plan(game);
+                self.inputs.insert(planned)
+            }
+        };
+        match inputs.pop_front() {
+            Some(action) => {
+                game.apply(action);
+                self.wait = INPUT_FRAMES;
+            }
+            None => game.tick(),
+        }
+    }
+
+    /// Returns the inputs placing the current piece: the best placement, or
+    /// a random one on a mistake.
+    fn plan(&mut self, game: &Game) -> VecDeque<Action> {
+        let mut placements = self.bot.placements(game);
+        placements.retain(|placement| placement.score.is_finite());
+        let chosen = if !placements.is_empty()
+            && self
+                .rng
+                .gen_bool(self.difficulty.mistake_rate.clamp(0.0, 1.0) as f64)
+        {
+            let index = self.rng.gen_range(0..placements.len());
+            Some(placements.swap_remove(index))
+        } else {
+            placements
+                .into_iter()
+                .max_by(|a, b| a.score.total_cmp(&b.score))
+        };
+
+        chosen
+            .map(|placement| placement.inputs.into())
+            .unwrap_or_default()
+    }
+
+    fn think_frames(&self) -> u32 {
+        let frames = (FPS / self.difficulty.pps.max(0.1)) as u32;
+        frames.saturating_sub(AVERAGE_INPUTS * INPUT_FRAMES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameOptions;
+
+    #[test]
+    fn test_speed() {
+        let mut cpu = CpuPlayer::new(Difficulty::MEDIUM, 1);
+        let mut game = Game::with_options(GameOptions {
+            seed: 1,
+            ..GameOptions::default()
+        });
+        let seconds = 10;
+        for _ in 0..seconds * FPS as u32 {
+            cpu.act(&mut game);
+            game.step();
+        }
+        let pieces = game.get_stats().pieces as f32;
+        let expected = Difficulty::MEDIUM.pps * seconds as f32;
+        assert!(
+            (pieces - expected).abs() <= expected * 0.25,
+            "placed {} pieces",
+            pieces
+        );
+        assert!(!game.is_game_over());
+    }
+
+    #[test]
+    fn test_mistakes() {
+        let perfect = Difficulty {
+            mistake_rate: 0.0,
+            ..Difficulty::EASY
+        };
+        let sloppy = Difficulty {
+            mistake_rate: 1.0,
+            ..Difficulty::EASY
+        };
+        let game = Game::new();
+        let best = Bot::default().best_inputs(&game);
+        let mut cpu = CpuPlayer::new(perfect, 1);
+        assert_eq!(Vec::from(cpu.plan(&game)), best);
+        let mut cpu = CpuPlayer::new(sloppy, 1);
+        assert!((0..10).any(|_| cpu.plan(&game) != best));
+    }
+}
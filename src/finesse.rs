@@ -0,0 +1,105 @@
+use crate::game::Action;
+
+/// What happens when a piece is placed with more inputs than needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FinesseTraining {
+    /// Faults are only counted.
+    #[default]
+    Off,
+    /// Faults are counted and the board flashes.
+    Flash,
+    /// The faulty piece is put back at its spawn position to be placed again.
+    Restart,
+}
+
+/// Finesse statistics of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Finesse {
+    pub pieces: u32,
+    pub clean_pieces: u32,
+    /// Pieces placed with more inputs than needed.
+    pub faults: u32,
+}
+
+impl Finesse {
+    /// Records the placement of a piece.
+    ///
+    /// # Arguments
+    ///
+    /// * `used` - The cost of the inputs the player used.
+    /// * `minimal` - The cost of the shortest inputs, or None if unknown.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the placement was a fault, otherwise false.
+    pub fn record(&mut self, used: u32, minimal: Option<u32>) -> bool {
+        let excess = used.saturating_sub(minimal.unwrap_or(used));
+        self.pieces += 1;
+        if excess > 0 {
+            self.faults += 1;
+        } else {
+            self.clean_pieces += 1;
+        }
+
+        excess > 0
+    }
+
+    /// Returns the share of pieces placed without a fault.
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - The percentage, 100 if no piece was placed yet.
+    pub fn percentage(&self) -> f32 {
+        if self.pieces == 0 {
+            100.0
+        } else {
+            self.clean_pieces as f32 * 100.0 / self.pieces as f32
+        }
+    }
+}
+
+/// Returns the finesse cost of inputs: the number of shifts and rotations.
+/// Drops are free, since they do not change where a piece ends up.
+///
+/// # Arguments
+///
+/// * `inputs` - The inputs used to place a piece.
+///
+/// # Returns
+///
+/// * `u32` - The cost of the inputs.
+pub fn input_cost(inputs: &[Action]) -> u32 {
+    inputs
+        .iter()
+        .filter(|action| !matches!(action, Action::SoftDrop | Action::HardDrop))
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_cost() {
+        let inputs = [
+            Action::MoveLeft,
+            Action::SoftDrop,
+            Action::RotateRight,
+            Action::HardDrop,
+        ];
+        assert_eq!(input_cost(&inputs), 2);
+    }
+
+    #[test]
+    fn test_record() {
+        let mut finesse = Finesse::default();
+        assert_eq!(finesse.percentage(), 100.0);
+        assert!(!finesse.record(2, Some(2)));
+        assert!(finesse.record(4, Some(1)));
+        assert!(!finesse.record(3, None));
+        assert_eq!(finesse.pieces, 3);
+        assert_eq!(finesse.clean_pieces, 2);
+        assert_eq!(finesse.faults, 1);
+        assert!((finesse.percentage() - 66.666).abs() < 0.01);
+    }
+}
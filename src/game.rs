@@ -22,19 +22,391 @@ impl Instant {
     }
 }
 
-use crate::pieces::{Color, PhysicalPiece, Piece, PIECES, PIECE_COUNT};
-use rand::Rng;
+use crate::board::Board;
+use crate::finesse::{self, Finesse, FinesseTraining};
+use crate::garbage;
+use crate::pieces::{PhysicalPiece, Piece, PieceKind, BLOCK_T, PIECES, PIECE_COUNT};
+use crate::puzzle::{Objective, Puzzle};
+use crate::stats::Stats;
+use crate::utils::Vector2;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 
+/// Mixed into the seed of a game to draw its garbage holes, for garbage not
+/// to change the pieces that follow.
+const GARBAGE_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+/// Id of the next game created, see `Game::id`.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Length of a logical frame: the game runs at 60 frames per second.
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Frames between two rows of gravity at the first level.
+const GRAVITY_FRAMES: u32 = 30;
+/// How much the time between two rows of gravity shrinks at each level.
+const GRAVITY_FACTOR: f32 = 0.8;
+/// Lines to clear to go up a level.
+const LINES_PER_LEVEL: u32 = 10;
+/// The highest level, where gravity stops speeding up.
+pub const MAX_LEVEL: u32 = 20;
+/// Smallest width and height of a grid, enough for any piece to spawn.
+pub const MIN_GRID_SIZE: u16 = 4;
+/// Largest width of a grid.
+pub const MAX_GRID_WIDTH: u16 = 32;
+/// Largest height of a grid.
+pub const MAX_GRID_HEIGHT: u16 = 40;
+/// Frames a piece resting on the stack waits before locking.
+const LOCK_DELAY_FRAMES: u32 = 30;
+/// Moves and rotations that can restart the lock delay of a piece.
+const LOCK_RESETS: u32 = 15;
+/// Most pieces that can be previewed after the current one.
+pub const MAX_PREVIEWS: usize = 5;
+/// Most frames run by one update, so that a long pause does not fast-forward.
+const MAX_FRAMES_PER_UPDATE: u32 = 10;
+
+/// The cells of the game grid, indexed by row then column.
+pub type Grid = Vec<Vec<Option<PieceKind>>>;
+
+/// How a new game is set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameOptions {
+    /// Seed of the random pieces and garbage: games with the same seed and
+    /// inputs play out the same.
+    pub seed: u64,
+    pub width: u16,
+    pub height: u16,
+    /// The level the game starts at, from 1 to `MAX_LEVEL`.
+    pub level: u32,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        GameOptions {
+            seed: rand::random(),
+            width: Game::GRID_WIDTH,
+            height: Game::GRID_HEIGHT,
+            level: 1,
+        }
+    }
+}
+
+/// The rules a game is played under.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameMode {
+    /// Endless play, scored by cleared lines.
+    Marathon,
+    /// The board starts with garbage rows that must all be cleared.
+    DigRace { rows: u16, messiness: f32 },
+    /// A preset board and piece sequence with an objective to reach.
+    Puzzle { objective: Objective },
+}
+
+/// An input the player can perform on the current piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateRight,
+    RotateLeft,
+    /// Rotates by 180 degrees at once.
+    RotateHalf,
+    SoftDrop,
+    HardDrop,
+    Hold,
+}
+
+impl Action {
+    /// Returns the action bound to a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `keycode` - The keycode of the input.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Action>` - The bound action, or None if the key is unbound.
+    pub fn from_key(keycode: char) -> Option<Action> {
+        match keycode {
+            'd' | '' => Some(Action::MoveRight),
+            'q' | '' => Some(Action::MoveLeft),
+            'z' | '' | 'c' => Some(Action::RotateRight),
+            'x' => Some(Action::RotateLeft),
+            'a' => Some(Action::RotateHalf),
+            's' => Some(Action::SoftDrop),
+            'h' => Some(Action::Hold),
+            ' ' => Some(Action::HardDrop),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the action in settings and replay files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::RotateRight => "rotate_right",
+            Action::RotateLeft => "rotate_left",
+            Action::RotateHalf => "rotate_half",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::Hold => "hold",
+        }
+    }
+
+    /// Returns the action with a name returned by `name`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the action.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Action>` - The action, or None if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Action> {
+        [
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::RotateRight,
+            Action::RotateLeft,
+            Action::RotateHalf,
+            Action::SoftDrop,
+            Action::HardDrop,
+            Action::Hold,
+        ]
+        .into_iter()
+        .find(|action| action.name() == name)
+    }
+}
+
+/// An input given to the game, recorded so that the game can be replayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// A key pressed, see `Game::press`.
+    Press(Action),
+    /// A key released, see `Game::release`.
+    Release(Action),
+    /// An action applied without holding a key, see `Game::apply`.
+    Apply(Action),
+}
+
+/// A final position the current piece can reach, with the cheapest inputs
+/// leading to it from where the piece is now: the fewest shifts and
+/// rotations, then the fewest drops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reachable {
+    pub piece: PhysicalPiece,
+    pub inputs: Vec<Action>,
+}
+
+/// The moves a placement search tries besides single shifts, quarter turns
+/// and drops.
+#[derive(Debug, Clone, Copy)]
+struct SearchMoves {
+    /// 180 turns, only counted by finesse with a key bound to them.
+    half_turns: bool,
+    /// Shifts held until the piece is blocked, one key press each.
+    shifts_to_wall: bool,
+}
+
+/// Something that happened in the game, reported to the front end so it can
+/// be animated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A piece locked on these cells.
+    Locked { cells: Vec<Vector2> },
+    /// These rows were full and are cleared, after the line clear delay.
+    LinesCleared { rows: Vec<u16> },
+    /// A piece was hard dropped by `distance` rows onto these cells.
+    HardDrop { cells: Vec<Vector2>, distance: u16 },
+}
+
+/// A line clear waiting for the line clear delay to pass.
+#[derive(Debug, Clone, Copy)]
+struct PendingClear {
+    frames_left: u32,
+    tspin: bool,
+    piece: Piece,
+}
+
+/// How held keys behave, in frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handling {
+    /// Frames a shift is held before it repeats (delayed auto shift).
+    pub das: u32,
+    /// Frames between two repeated shifts (auto repeat rate), 0 to shift
+    /// straight to the wall.
+    pub arr: u32,
+    /// Frames between two rows while soft drop is held, 0 to drop straight
+    /// to the stack.
+    pub soft_drop: u32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: 10,
+            arr: 2,
+            soft_drop: 2,
+        }
+    }
+}
+
+/// Offsets tried in turn when a rotation is blocked in place, for each
+/// rotation the piece starts from, with `y` down.
+pub type KickTable = [&'static [Vector2]; 4];
+
+/// The 180 degree kicks of SRS+, the rotation system of modern games.
+pub const HALF_TURN_KICKS: KickTable = [
+    &[
+        Vector2::new(0, -1),
+        Vector2::new(1, -1),
+        Vector2::new(-1, -1),
+        Vector2::new(1, 0),
+        Vector2::new(-1, 0),
+    ],
+    &[
+        Vector2::new(1, 0),
+        Vector2::new(1, -2),
+        Vector2::new(1, -1),
+        Vector2::new(0, -2),
+        Vector2::new(0, -1),
+    ],
+    &[
+        Vector2::new(0, 1),
+        Vector2::new(-1, 1),
+        Vector2::new(1, 1),
+        Vector2::new(-1, 0),
+        Vector2::new(1, 0),
+    ],
+    &[
+        Vector2::new(-1, 0),
+        Vector2::new(-1, -2),
+        Vector2::new(-1, -1),
+        Vector2::new(0, -2),
+        Vector2::new(0, -1),
+    ],
+];
+
+/// No kicks: a blocked rotation fails.
+pub const NO_KICKS: KickTable = [&[]; 4];
+
+/// A shift key being held.
+#[derive(Debug, Clone, Copy)]
+struct Shift {
+    action: Action,
+    frames: u32,
+}
+
+/// The lines cleared by the last locked piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LineClear {
+    pub lines: u8,
+    pub tspin: bool,
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Returns the garbage lines sent by the clear.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The attack of the clear.
+    pub fn attack(&self) -> u32 {
+        let lines = match (self.tspin, self.lines) {
+            (_, 0) => 0,
+            (true, n) => 2 * n as u32,
+            (false, n) if n >= 4 => n as u32,
+            (false, n) => n as u32 - 1,
+        };
+        if self.perfect_clear {
+            lines + 10
+        } else {
+            lines
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Game {
-    grid: [[Option<Color>; Game::GRID_WIDTH as usize]; Game::GRID_HEIGHT as usize],
+    /// Tells this game apart from the other games created, kept by copies.
+    id: u64,
+    board: Board,
     current: PhysicalPiece,
-    next: Piece,
+    next: Option<Piece>,
+    /// The pieces after the next one.
+    queue: VecDeque<Piece>,
+    /// Whether the queue is a fixed sequence instead of random pieces.
+    fixed_queue: bool,
     held: Option<Piece>,
+    hold_allowed: bool,
     has_held: bool,
     score: u32,
-    rng: rand::rngs::ThreadRng,
+    lines: u32,
+    last_clear: LineClear,
+    last_rotated: bool,
+    piece_start: PhysicalPiece,
+    piece_inputs: Vec<Action>,
+    finesse: Finesse,
+    finesse_training: FinesseTraining,
+    /// Whether locked pieces are checked for finesse, off on the copies the
+    /// bot plays ahead on.
+    finesse_checked: bool,
+    /// Whether the shortest inputs checked for finesse can use 180 turns,
+    /// for players with a key bound to them.
+    finesse_half_turns: bool,
+    stats: Stats,
+    options: GameOptions,
+    rng: StdRng,
+    /// Draws garbage holes, apart from `rng` so that games with the same seed
+    /// get the same pieces whatever garbage they receive.
+    garbage_rng: StdRng,
     time: Instant,
     game_over: bool,
+    mode: GameMode,
+    garbage_remaining: u16,
+    /// Garbage lines received from an opponent, raised when a piece locks
+    /// without clearing lines.
+    incoming_garbage: u32,
+    /// Garbage lines sent by clears and not yet taken by `take_attack`.
+    outgoing_garbage: u32,
+    started: Instant,
+    finished_in: Option<Duration>,
+    completed: bool,
+    events: Vec<Event>,
+    line_clear_delay: Duration,
+    pending_clear: Option<PendingClear>,
+    /// Time between a lock, or the clear that follows it, and the spawn of
+    /// the next piece (ARE).
+    entry_delay: Duration,
+    /// Frames left before the next piece spawns, during the entry delay.
+    pending_spawn: Option<u32>,
+    /// Rotation pressed while no piece was in play, given to the next piece
+    /// as it spawns.
+    initial_rotation: Option<Action>,
+    /// Whether hold was pressed while no piece was in play, holding the next
+    /// piece as it spawns.
+    initial_hold: bool,
+    dirty: HashSet<(u16, u16)>,
+    accumulator: Duration,
+    gravity_frames: u32,
+    lock_frames: u32,
+    lock_resets: u32,
+    shift: Option<Shift>,
+    soft_drop_held: bool,
+    handling: Handling,
+    /// Kicks of the 180 degree rotation.
+    half_turn_kicks: KickTable,
+    /// Frames run since the game started.
+    frame: u64,
+    /// Whether inputs are recorded, only for the player's game.
+    recording: bool,
+    /// Every input given so far, with the frame it was given at, when
+    /// recording.
+    inputs: Vec<(u64, Input)>,
+    /// Recorded inputs still to be given, when replaying a game.
+    playback: VecDeque<(u64, Input)>,
+    replaying: bool,
 }
 
 impl Game {
@@ -47,62 +419,493 @@ impl Game {
     ///
     /// * `Game` - A new game instance.
     pub fn new() -> Game {
-        let mut rng = rand::thread_rng();
+        Game::with_options(GameOptions::default())
+    }
+
+    /// Creates a new game with a given seed, grid size and level.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of the game. The grid size and level are
+    ///   clamped to the supported range.
+    ///
+    /// # Returns
+    ///
+    /// * `Game` - A new game instance.
+    pub fn with_options(options: GameOptions) -> Game {
+        let options = GameOptions {
+            width: options.width.clamp(MIN_GRID_SIZE, MAX_GRID_WIDTH),
+            height: options.height.clamp(MIN_GRID_SIZE, MAX_GRID_HEIGHT),
+            level: options.level.clamp(1, MAX_LEVEL),
+            ..options
+        };
+        let (width, height) = (options.width, options.height);
+        let mut rng = StdRng::seed_from_u64(options.seed);
+        let current = Game::spawn_position(*PIECES[rng.gen_range(0..PIECE_COUNT)], width);
 
         Game {
-            grid: [[None; Game::GRID_WIDTH as usize]; Game::GRID_HEIGHT as usize],
-            current: PhysicalPiece {
-                x: Game::GRID_WIDTH as i16 / 2i16 - 2i16,
-                y: -1,
-                rotation: 0,
-                piece: *PIECES[rng.gen_range(0..PIECE_COUNT)],
-            },
-            next: *PIECES[rng.gen_range(0..PIECE_COUNT)],
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            board: Board::new(width, height),
+            piece_start: current.clone(),
+            current,
+            next: Some(*PIECES[rng.gen_range(0..PIECE_COUNT)]),
+            queue: (1..MAX_PREVIEWS)
+                .map(|_| *PIECES[rng.gen_range(0..PIECE_COUNT)])
+                .collect(),
+            fixed_queue: false,
             held: None,
+            hold_allowed: true,
             has_held: false,
             score: 0,
+            lines: 0,
+            last_clear: LineClear::default(),
+            last_rotated: false,
+            piece_inputs: Vec::new(),
+            finesse: Finesse::default(),
+            finesse_training: FinesseTraining::Off,
+            finesse_checked: true,
+            finesse_half_turns: false,
+            stats: Stats::default(),
+            options,
             rng,
+            garbage_rng: StdRng::seed_from_u64(options.seed ^ GARBAGE_SEED),
             time: Instant::now(),
             game_over: false,
+            mode: GameMode::Marathon,
+            garbage_remaining: 0,
+            incoming_garbage: 0,
+            outgoing_garbage: 0,
+            started: Instant::now(),
+            finished_in: None,
+            completed: false,
+            events: Vec::new(),
+            line_clear_delay: Duration::ZERO,
+            pending_clear: None,
+            entry_delay: Duration::ZERO,
+            pending_spawn: None,
+            initial_rotation: None,
+            initial_hold: false,
+            dirty: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .collect(),
+            accumulator: Duration::ZERO,
+            gravity_frames: 0,
+            lock_frames: 0,
+            lock_resets: 0,
+            shift: None,
+            soft_drop_held: false,
+            handling: Handling::default(),
+            half_turn_kicks: HALF_TURN_KICKS,
+            frame: 0,
+            recording: false,
+            inputs: Vec::new(),
+            playback: VecDeque::new(),
+            replaying: false,
         }
     }
 
-    /// Updates the game state.
+    /// Creates a new dig race, with the bottom of the grid filled with garbage.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - The number of garbage rows, capped to leave room to spawn.
+    /// * `messiness` - The chance, between 0 and 1, that the hole changes column
+    ///   from one row to the next.
+    ///
+    /// # Returns
+    ///
+    /// * `Game` - A new game instance.
+    pub fn new_dig_race(rows: u16, messiness: f32) -> Game {
+        Game::new_dig_race_with_options(GameOptions::default(), rows, messiness)
+    }
+
+    /// Creates a new dig race with a given seed, grid size and level.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of the game.
+    /// * `rows` - The number of garbage rows, capped to leave room to spawn.
+    /// * `messiness` - The chance that the hole changes column between rows.
+    ///
+    /// # Returns
+    ///
+    /// * `Game` - A new game instance.
+    pub fn new_dig_race_with_options(options: GameOptions, rows: u16, messiness: f32) -> Game {
+        let mut game = Game::with_options(options);
+        let (width, height) = (game.width(), game.height());
+        let rows = rows.min(height - 4);
+        let garbage = garbage::generate_rows(
+            &mut game.garbage_rng,
+            rows as usize,
+            width as usize,
+            messiness,
+        );
+        let first = (height - rows) as usize;
+        for (i, row) in garbage.into_iter().enumerate() {
+            game.board.set_row((first + i) as u16, row);
+        }
+        game.mode = GameMode::DigRace { rows, messiness };
+        game.garbage_remaining = rows;
+        game
+    }
+
+    /// Creates a new game from a puzzle, drawing pieces from its fixed queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `puzzle` - The puzzle to play.
+    ///
+    /// # Returns
+    ///
+    /// * `Game` - A new game instance.
+    pub fn new_puzzle(puzzle: &Puzzle) -> Game {
+        let mut game = Game::new();
+        let mut queue: VecDeque<Piece> = puzzle.queue.iter().copied().collect();
+        game.board = Board::from_grid(puzzle.grid.clone());
+        game.current = Game::spawn_position(
+            queue.pop_front().expect("empty puzzle queue"),
+            Game::GRID_WIDTH,
+        );
+        game.start_piece();
+        game.next = queue.pop_front();
+        game.queue = queue;
+        game.fixed_queue = true;
+        game.hold_allowed = puzzle.hold_allowed;
+        game.mode = GameMode::Puzzle {
+            objective: puzzle.objective,
+        };
+        game
+    }
+
+    /// Places a piece where the guideline spawns it: in its spawn rotation,
+    /// centred in the grid, or one column left of centre when it cannot be,
+    /// with its lowest cells on the top row.
+    fn spawn_position(piece: Piece, width: u16) -> PhysicalPiece {
+        let (corner, size) = piece.bounding_box(0);
+        PhysicalPiece {
+            x: (width as i16 - piece.size() as i16) / 2,
+            y: -(corner.y + size.y - 1),
+            rotation: 0,
+            piece,
+        }
+    }
+
+    /// Runs the frames due since the last update, at a fixed rate of one
+    /// frame per `FRAME` whatever the rate of the caller.
     pub fn update(&mut self) {
+        for _ in 0..self.frames_due() {
+            self.step();
+        }
+    }
+
+    /// Returns the number of frames due since the last call, one per `FRAME`,
+    /// for callers that run something before each `step`.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The frames to run, at most `MAX_FRAMES_PER_UPDATE`.
+    pub fn frames_due(&mut self) -> u32 {
         let now = Instant::now();
-        let delta = now.duration_since(self.time).as_millis();
-        if delta > 150 {
-            self.tick();
-            self.time = now;
+        self.accumulator += now.duration_since(self.time);
+        self.time = now;
+        let mut frames = 0;
+        while self.accumulator >= FRAME {
+            if frames == MAX_FRAMES_PER_UPDATE {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= FRAME;
+            frames += 1;
+        }
+
+        frames
+    }
+
+    /// Advances the game by one frame: line clear and entry delays, auto
+    /// shift, gravity and lock delay.
+    pub fn step(&mut self) {
+        if self.game_over {
+            return;
+        }
+        while let Some(&(_, input)) = self.playback.front().filter(|(f, _)| *f <= self.frame) {
+            self.playback.pop_front();
+            match input {
+                Input::Press(action) => self.press(action),
+                Input::Release(action) => self.release(action),
+                Input::Apply(action) => {
+                    self.apply(action);
+                }
+            }
+        }
+        self.frame += 1;
+        if self.is_between_pieces() {
+            self.charge_shift();
+        }
+        if let Some(pending) = &mut self.pending_clear {
+            if pending.frames_left > 1 {
+                pending.frames_left -= 1;
+            } else {
+                let pending = *pending;
+                self.pending_clear = None;
+                self.finish_lock(pending.tspin, pending.piece);
+            }
+            return;
+        }
+        if let Some(frames) = &mut self.pending_spawn {
+            if *frames > 1 {
+                *frames -= 1;
+            } else {
+                self.pending_spawn = None;
+                self.spawn_next();
+            }
+            return;
+        }
+
+        // Delayed auto shift
+        if let Some(shift) = &mut self.shift {
+            shift.frames += 1;
+            let Shift { action, frames } = *shift;
+            let Handling { das, arr, .. } = self.handling;
+            if frames >= das && (arr == 0 || (frames - das).is_multiple_of(arr)) {
+                let func = if action == Action::MoveLeft {
+                    PhysicalPiece::move_left
+                } else {
+                    PhysicalPiece::move_right
+                };
+                let mut moved = false;
+                while !self.move_and_collide(func) {
+                    moved = true;
+                    if arr > 0 {
+                        break;
+                    }
+                }
+                if moved {
+                    self.reset_lock_delay();
+                }
+            }
+        }
+
+        // Gravity and lock delay
+        let mut below = self.current.clone();
+        below.newton();
+        if self.collides(&below) {
+            self.lock_frames += 1;
+            if self.lock_frames >= LOCK_DELAY_FRAMES {
+                self.tick();
+            }
+        } else {
+            self.gravity_frames += 1;
+            let interval = if self.soft_drop_held {
+                self.handling.soft_drop
+            } else {
+                self.gravity_interval()
+            };
+            if self.gravity_frames >= interval {
+                self.gravity_frames = 0;
+                while !self.move_and_collide(PhysicalPiece::newton) && interval == 0 {}
+            }
+        }
+    }
+
+    /// Returns the frames between two rows of gravity at the current level,
+    /// at least one.
+    fn gravity_interval(&self) -> u32 {
+        let frames = GRAVITY_FRAMES as f32 * GRAVITY_FACTOR.powi(self.get_level() as i32 - 1);
+        (frames.round() as u32).max(1)
+    }
+
+    /// Keeps charging a held shift while no piece is in play, so that a
+    /// charged shift moves the next piece on its first frame.
+    fn charge_shift(&mut self) {
+        if let Some(shift) = &mut self.shift {
+            shift.frames = (shift.frames + 1).min(self.handling.das.saturating_sub(1));
         }
     }
 
-    fn tick(&mut self) {
+    /// Restarts the lock delay after the piece moved, a limited number of
+    /// times per piece.
+    fn reset_lock_delay(&mut self) {
+        if self.lock_frames > 0 && self.lock_resets < LOCK_RESETS {
+            self.lock_frames = 0;
+            self.lock_resets += 1;
+        }
+    }
+
+    /// Moves the current piece down one row, locking it if it cannot move.
+    pub fn tick(&mut self) {
+        if self.game_over || self.is_between_pieces() {
+            return;
+        }
         if self.move_and_collide(PhysicalPiece::newton) {
+            if self.finesse_checked
+                && self.check_finesse()
+                && self.finesse_training == FinesseTraining::Restart
+            {
+                self.current = self.piece_start.clone();
+                self.start_piece();
+                return;
+            }
+            let tspin = self.is_tspin();
+            let piece = self.current.piece;
+            let cells = self.current.cells();
             if self.boup() {
-                self.game_over = true;
+                self.end();
+            }
+            self.events.push(Event::Locked { cells });
+            let rows = self.board.full_rows();
+            if !rows.is_empty() {
+                self.events.push(Event::LinesCleared { rows });
+                let frames = delay_frames(self.line_clear_delay);
+                if !self.game_over && frames > 0 {
+                    self.pending_clear = Some(PendingClear {
+                        frames_left: frames,
+                        tspin,
+                        piece,
+                    });
+                    return;
+                }
             }
-            let cleared = self.clear_lines();
-            self.score += self.compute_score(cleared);
-            self.has_held = false;
+            self.finish_lock(tspin, piece);
+        }
+    }
+
+    /// Clears the full rows left by a locked piece, scores them and spawns
+    /// the next piece, after the entry delay.
+    fn finish_lock(&mut self, tspin: bool, piece: Piece) {
+        let cleared = self.clear_lines();
+        // Scored at the level the lines were cleared at
+        self.score += self.compute_score(cleared);
+        self.lines += cleared as u32;
+        self.last_clear = LineClear {
+            lines: cleared,
+            tspin,
+            perfect_clear: cleared > 0 && self.board.is_empty(),
+        };
+        self.stats.record_lock(&piece, &self.last_clear);
+        self.has_held = false;
+        // A clear cancels the garbage received first and sends the rest
+        let attack = self.last_clear.attack();
+        let cancelled = attack.min(self.incoming_garbage);
+        self.incoming_garbage -= cancelled;
+        self.outgoing_garbage += attack - cancelled;
+        if cleared == 0 && self.incoming_garbage > 0 {
+            self.raise_garbage();
+        }
+        self.check_completed();
+        if !self.game_over {
+            match delay_frames(self.entry_delay) {
+                0 => self.spawn_next(),
+                frames => self.pending_spawn = Some(frames),
+            }
+        }
+    }
+
+    /// Spawns the next piece after a lock, with the hold and rotation
+    /// pressed while waiting for it.
+    fn spawn_next(&mut self) {
+        self.spawn_new();
+        self.apply_initial_actions();
+    }
+
+    /// Applies the hold and rotation pressed before the current piece
+    /// spawned: the Initial Hold and Initial Rotation Systems. A rotation
+    /// blocked where the piece spawns is dropped.
+    fn apply_initial_actions(&mut self) {
+        if std::mem::take(&mut self.initial_hold) && self.can_hold() {
+            self.hold();
+        }
+        let rotate = match self.initial_rotation.take() {
+            Some(Action::RotateLeft) => PhysicalPiece::rotate_left,
+            Some(Action::RotateRight) => PhysicalPiece::rotate_right,
+            Some(Action::RotateHalf) => PhysicalPiece::rotate_half,
+            _ => return,
+        };
+        if !self.move_and_collide(rotate) {
+            self.last_rotated = false;
+            self.start_piece();
+        }
+    }
+
+    /// Records the finesse of the current piece before it locks.
+    /// Returns true if the piece was placed with more inputs than needed.
+    fn check_finesse(&mut self) -> bool {
+        let used = finesse::input_cost(&self.piece_inputs);
+        let minimal = if used == 0 {
+            Some(0)
+        } else {
+            let mut cells = self.current.cells();
+            cells.sort();
+            let moves = SearchMoves {
+                half_turns: self.finesse_half_turns,
+                shifts_to_wall: true,
+            };
+            self.search(&self.piece_start, Some(&cells), moves)
+                .first()
+                .map(|reachable| finesse::input_cost(&reachable.inputs))
+        };
+
+        self.finesse.record(used, minimal)
+    }
+
+    fn start_piece(&mut self) {
+        self.piece_start = self.current.clone();
+        self.piece_inputs.clear();
+        self.gravity_frames = 0;
+        self.lock_frames = 0;
+        self.lock_resets = 0;
+    }
+
+    fn check_completed(&mut self) {
+        let completed = match self.mode {
+            GameMode::Marathon => false,
+            GameMode::DigRace { .. } => self.garbage_remaining == 0,
+            GameMode::Puzzle { objective } => objective.is_met(&self.last_clear, self.lines),
+        };
+        if completed && !self.completed {
+            self.completed = true;
+            self.end();
+        }
+    }
+
+    fn end(&mut self) {
+        if self.finished_in.is_none() {
+            self.finished_in = Some(Instant::now().duration_since(self.started));
+        }
+        self.game_over = true;
+    }
+
+    /// Returns true if the current piece is a T that got into place by rotating
+    /// and has at least three of the corners of its box blocked.
+    fn is_tspin(&self) -> bool {
+        if !self.last_rotated || self.current.piece != BLOCK_T {
+            return false;
         }
+        let side = self.current.piece.size() as i16 - 1;
+        let corners = [(0, 0), (side, 0), (0, side), (side, side)];
+        let blocked = corners
+            .iter()
+            .filter(|(x, y)| {
+                self.board
+                    .is_blocked(self.current.x + x, self.current.y + y)
+            })
+            .count();
+
+        blocked >= 3
     }
 
     fn hold(&mut self) {
-        if self.has_held {
+        if !self.can_hold() {
             return;
-        } else if self.held.is_none() {
+        }
+        self.stats.record_hold();
+        if self.held.is_none() {
             self.held = Some(self.current.piece);
             self.spawn_new();
         } else {
             let bkp = self.held;
             self.held = Some(self.current.piece);
-            self.current = PhysicalPiece {
-                x: Game::GRID_WIDTH as i16 / 2 - 2,
-                y: -1,
-                rotation: 0,
-                piece: bkp.unwrap(),
-            };
+            self.current = Game::spawn_position(bkp.unwrap(), self.width());
+            self.start_piece();
         }
         self.has_held = true;
     }
@@ -113,187 +916,876 @@ impl Game {
     ///
     /// * `keycode` - The keycode of the input.
     pub fn handle_input(&mut self, keycode: char) {
-        match keycode {
-            'd' | '' => self.move_and_collide(PhysicalPiece::move_right),
-            'q' | '' => self.move_and_collide(PhysicalPiece::move_left),
-            'z' | '' => self.move_and_collide(PhysicalPiece::rotate_right),
-            'c' => self.move_and_collide(PhysicalPiece::rotate_right),
-            'x' => self.move_and_collide(PhysicalPiece::rotate_left),
-            's' => self.move_and_collide(PhysicalPiece::newton),
-            'h' => {
+        if let Some(action) = Action::from_key(keycode) {
+            self.apply(action);
+        }
+    }
+
+    /// Applies an action to the current piece.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action to apply.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the action was blocked, otherwise false.
+    pub fn apply(&mut self, action: Action) -> bool {
+        self.record(Input::Apply(action));
+        self.apply_action(action)
+    }
+
+    fn apply_action(&mut self, action: Action) -> bool {
+        if self.game_over {
+            return true;
+        }
+        if self.is_between_pieces() {
+            // Kept for the next piece
+            return match action {
+                Action::RotateLeft | Action::RotateRight | Action::RotateHalf => {
+                    self.initial_rotation = Some(action);
+                    false
+                }
+                Action::Hold if self.hold_allowed => {
+                    self.initial_hold = true;
+                    false
+                }
+                _ => true,
+            };
+        }
+        self.stats.record_key();
+        if action != Action::Hold {
+            self.piece_inputs.push(action);
+        }
+        let blocked = match action {
+            Action::MoveRight => self.move_and_collide(PhysicalPiece::move_right),
+            Action::MoveLeft => self.move_and_collide(PhysicalPiece::move_left),
+            Action::RotateRight => self.move_and_collide(PhysicalPiece::rotate_right),
+            Action::RotateLeft => self.move_and_collide(PhysicalPiece::rotate_left),
+            Action::RotateHalf => match self.half_turned(&self.current) {
+                Some(turned) => {
+                    self.current = turned;
+                    self.last_rotated = true;
+                    false
+                }
+                None => true,
+            },
+            Action::SoftDrop => self.move_and_collide(PhysicalPiece::newton),
+            Action::Hold => {
+                let blocked = !self.can_hold();
                 self.hold();
-                true
+                blocked
             }
-            ' ' => {
-                while !self.move_and_collide(PhysicalPiece::newton) {}
-                true
+            Action::HardDrop => {
+                let mut distance = 0;
+                while !self.move_and_collide(PhysicalPiece::newton) {
+                    distance += 1;
+                }
+                self.events.push(Event::HardDrop {
+                    cells: self.current.cells(),
+                    distance,
+                });
+                false
             }
-            _ => false,
         };
+        match action {
+            Action::SoftDrop if !blocked => self.gravity_frames = 0,
+            Action::MoveLeft
+            | Action::MoveRight
+            | Action::RotateRight
+            | Action::RotateLeft
+            | Action::RotateHalf
+                if !blocked =>
+            {
+                self.reset_lock_delay()
+            }
+            _ => {}
+        }
+
+        blocked
+    }
+
+    /// Presses the key of an action: the action is applied at once, shifts
+    /// then repeat after a delay and soft drop speeds up gravity until the key
+    /// is released. A hard dropped piece locks at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action of the pressed key.
+    pub fn press(&mut self, action: Action) {
+        self.record(Input::Press(action));
+        match action {
+            Action::MoveLeft | Action::MoveRight => {
+                self.shift = Some(Shift { action, frames: 0 });
+            }
+            Action::SoftDrop => self.soft_drop_held = true,
+            _ => {}
+        }
+        self.apply_action(action);
+        if action == Action::HardDrop {
+            self.tick();
+        }
+    }
+
+    /// Releases the key of an action, stopping its repetition.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action of the released key.
+    pub fn release(&mut self, action: Action) {
+        self.record(Input::Release(action));
+        match action {
+            Action::MoveLeft | Action::MoveRight
+                if self.shift.is_some_and(|shift| shift.action == action) =>
+            {
+                self.shift = None;
+            }
+            Action::SoftDrop => self.soft_drop_held = false,
+            _ => {}
+        }
     }
 
     fn clear_lines(&mut self) -> u8 {
-        let mut cleared = 0u8;
-        let width = Game::GRID_WIDTH as usize;
-        let height = Game::GRID_HEIGHT as usize;
-        let mut y = height;
-        while y > 0 {
-            y -= 1;
-            let row = &self.grid[y];
-            let mut x = 0usize;
-            while x < width as usize && row[x].is_some() {
-                x += 1;
+        let width = self.width();
+        let rows = self.board.full_rows();
+        for &y in &rows {
+            let row = self.board.remove_row(y);
+            if garbage::is_garbage_row(&row) {
+                self.garbage_remaining = self.garbage_remaining.saturating_sub(1);
             }
-            // If line cleared
-            if x == width {
-                let mut s_y = y;
-                while s_y > 0 {
-                    self.grid[s_y] = self.grid[s_y - 1];
-                    s_y -= 1;
-                }
-                cleared += 1 + self.clear_lines();
-                y = 0;
+        }
+        // Every row above the lowest cleared one moved down
+        if let Some(&lowest) = rows.last() {
+            for y in 0..=lowest {
+                self.dirty.extend((0..width).map(|x| (x, y)));
             }
         }
 
-        cleared
+        rows.len() as u8
+    }
+
+    /// Pushes the received garbage up from the bottom of the grid, with a
+    /// single hole shared by all of its rows. Cells pushed out of the top end
+    /// the game.
+    fn raise_garbage(&mut self) {
+        let (width, height) = (self.width() as usize, self.height() as usize);
+        let count = (std::mem::take(&mut self.incoming_garbage) as usize).min(height);
+        let rows = garbage::generate_rows(&mut self.garbage_rng, count, width, 0.0);
+        let topped_out = self.board.push_up(rows);
+        self.dirty
+            .extend((0..height as u16).flat_map(|y| (0..width as u16).map(move |x| (x, y))));
+        if topped_out {
+            self.end();
+        }
     }
 
     fn boup(&mut self) -> bool {
         // Save current piece in grid
-        let shape = self.current.get_shape();
-        for i in 0..4 {
-            let p = shape[i];
-            let p_x = self.current.x + p.0 as i16;
-            let p_y = self.current.y + p.1 as i16;
-            if p_y < 0 {
+        for cell in self.current.cells() {
+            if cell.y < 0 {
                 return true;
             }
-            self.grid[p_y as usize][p_x as usize] = Some(self.current.piece.color);
+            let (x, y) = (cell.x as u16, cell.y as u16);
+            self.board.set(x, y, Some(self.current.piece.kind));
+            self.dirty.insert((x, y));
         }
-        self.spawn_new();
         false
     }
 
-    fn spawn_new(&mut self) {
-        self.current = PhysicalPiece {
-            x: Game::GRID_WIDTH as i16 / 2 - 2,
-            y: -1,
-            rotation: 0,
-            piece: self.next,
-        };
-        self.next = *PIECES[self.rng.gen_range(0..PIECE_COUNT)];
+    fn spawn_new(&mut self) {
+        match self.next.take() {
+            Some(piece) => {
+                self.current = Game::spawn_position(piece, self.width());
+                self.next = self.draw_piece();
+                self.start_piece();
+            }
+            // A fixed queue ran out of pieces
+            None => self.end(),
+        }
+    }
+
+    fn draw_piece(&mut self) -> Option<Piece> {
+        if !self.fixed_queue {
+            let piece = *PIECES[self.rng.gen_range(0..PIECE_COUNT)];
+            self.queue.push_back(piece);
+        }
+        self.queue.pop_front()
+    }
+
+    /// Returns true if a collision occurred
+    fn move_and_collide(&mut self, func: fn(&mut PhysicalPiece)) -> bool {
+        let mut test_piece = self.current.clone();
+        func(&mut test_piece);
+        if self.collides(&test_piece) {
+            true
+        } else {
+            self.last_rotated = test_piece.rotation != self.current.rotation;
+            func(&mut self.current);
+            false
+        }
+    }
+
+    /// Returns every distinct position the current piece can lock in, through
+    /// shifts, rotations, soft and hard drops, ignoring gravity. Positions
+    /// covering the same cells are only returned once, with the cheapest inputs.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Reachable>` - The reachable positions.
+    pub fn reachable_placements(&self) -> Vec<Reachable> {
+        let moves = SearchMoves {
+            half_turns: true,
+            shifts_to_wall: false,
+        };
+        self.search(&self.current, None, moves)
+    }
+
+    /// Searches the lockable positions from a start position. If a target is
+    /// given, the search stops once the position covering those cells is found.
+    /// A shift to the wall shows in the inputs as a single shift.
+    fn search(
+        &self,
+        start: &PhysicalPiece,
+        target: Option<&[Vector2]>,
+        moves: SearchMoves,
+    ) -> Vec<Reachable> {
+        const ACTIONS: [Action; 7] = [
+            Action::MoveLeft,
+            Action::MoveRight,
+            Action::RotateRight,
+            Action::RotateLeft,
+            Action::RotateHalf,
+            Action::SoftDrop,
+            Action::HardDrop,
+        ];
+        let mut placements = Vec::new();
+        let mut placed = HashSet::new();
+        let mut settled = HashSet::new();
+        // Every position reached with the index of the one it was reached from
+        let mut nodes: Vec<(PhysicalPiece, Option<(usize, Action)>)> = Vec::new();
+        let mut queue = BinaryHeap::new();
+        nodes.push((start.clone(), None));
+        queue.push(Reverse((0, 0, 0)));
+
+        // Fewest shifts and rotations first, drops being free, then fewest
+        // inputs, so the first path settled to a position is the cheapest
+        while let Some(Reverse((cost, steps, index))) = queue.pop() {
+            let piece = nodes[index].0.clone();
+            if !settled.insert((piece.x, piece.y, piece.rotation)) {
+                continue;
+            }
+            let mut below = piece.clone();
+            below.newton();
+            if self.collides(&below) {
+                let mut cells = piece.cells();
+                cells.sort();
+                if target.is_none_or(|target| target == cells) && placed.insert(cells) {
+                    let mut inputs = Vec::new();
+                    let mut node = index;
+                    while let Some((parent, action)) = nodes[node].1 {
+                        inputs.push(action);
+                        node = parent;
+                    }
+                    inputs.reverse();
+                    placements.push(Reachable {
+                        piece: piece.clone(),
+                        inputs,
+                    });
+                    if target.is_some() {
+                        break;
+                    }
+                }
+            }
+            let moved = ACTIONS
+                .into_iter()
+                .filter(|&action| moves.half_turns || action != Action::RotateHalf)
+                .map(|action| (action, self.moved(&piece, action)));
+            let shifted_to_wall = [Action::MoveLeft, Action::MoveRight]
+                .into_iter()
+                .filter(|_| moves.shifts_to_wall)
+                .map(|action| (action, self.shifted_to_wall(&piece, action)));
+            for (action, next) in moved.chain(shifted_to_wall) {
+                if let Some(next) = next {
+                    if !settled.contains(&(next.x, next.y, next.rotation)) {
+                        nodes.push((next, Some((index, action))));
+                        let cost = cost + finesse::input_cost(&[action]);
+                        queue.push(Reverse((cost, steps + 1, nodes.len() - 1)));
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+
+    /// Returns the piece turned by 180 degrees, in place or moved by the
+    /// first kick that fits, or None if every position is blocked.
+    fn half_turned(&self, piece: &PhysicalPiece) -> Option<PhysicalPiece> {
+        let mut turned = piece.clone();
+        turned.rotate_half();
+        std::iter::once(Vector2::default())
+            .chain(self.half_turn_kicks[piece.rotation].iter().copied())
+            .map(|kick| PhysicalPiece {
+                x: turned.x + kick.x,
+                y: turned.y + kick.y,
+                ..turned.clone()
+            })
+            .find(|kicked| !self.collides(kicked))
+    }
+
+    /// Returns the piece shifted as far as it goes, as auto shift moves it while
+    /// the key is held, or None if the shift is blocked.
+    fn shifted_to_wall(&self, piece: &PhysicalPiece, action: Action) -> Option<PhysicalPiece> {
+        let mut shifted = self.moved(piece, action)?;
+        while let Some(next) = self.moved(&shifted, action) {
+            shifted = next;
+        }
+
+        Some(shifted)
+    }
+
+    /// Returns the piece after the action, or None if the action is blocked.
+    fn moved(&self, piece: &PhysicalPiece, action: Action) -> Option<PhysicalPiece> {
+        let mut next = piece.clone();
+        match action {
+            Action::MoveRight => next.move_right(),
+            Action::MoveLeft => next.move_left(),
+            Action::RotateRight => next.rotate_right(),
+            Action::RotateLeft => next.rotate_left(),
+            Action::RotateHalf => return self.half_turned(piece),
+            Action::SoftDrop => next.newton(),
+            Action::HardDrop => {
+                let mut below = next.clone();
+                below.newton();
+                if self.collides(&below) {
+                    return None;
+                }
+                while !self.collides(&below) {
+                    next = below.clone();
+                    below.newton();
+                }
+            }
+            Action::Hold => return None,
+        }
+        if self.collides(&next) {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Returns true if the piece overlaps the walls, the floor or a filled cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `piece` - The piece to test.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the piece collides, otherwise false.
+    pub fn collides(&self, piece: &PhysicalPiece) -> bool {
+        self.board.collides(piece)
+    }
+
+    fn compute_score(&self, cleared: u8) -> u32 {
+        let base = match cleared {
+            0 => 0,
+            1 => 40,
+            2 => 100,
+            3 => 300,
+            4 => 1200,
+            // Only pieces taller than a tetromino clear more lines
+            n => 300 * n as u32,
+        };
+
+        base * self.get_level()
+    }
+
+    /// Returns the game grid.
+    ///
+    /// # Returns
+    ///
+    /// * `&Grid` - The game grid.
+    pub fn get_grid(&self) -> &Grid {
+        self.board.cells()
+    }
+
+    /// Returns the packed cells of the game grid.
+    ///
+    /// # Returns
+    ///
+    /// * `&Board` - The filled cells and what fills them.
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the number of columns of the grid.
+    pub fn width(&self) -> u16 {
+        self.board.width()
+    }
+
+    /// Returns the number of rows of the grid.
+    pub fn height(&self) -> u16 {
+        self.board.height()
+    }
+
+    /// Returns the current level, which goes up every 10 cleared lines.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The level, from 1 to `MAX_LEVEL`.
+    pub fn get_level(&self) -> u32 {
+        (self.options.level + self.lines / LINES_PER_LEVEL).min(MAX_LEVEL)
+    }
+
+    /// Returns the options the game was created with.
+    ///
+    /// # Returns
+    ///
+    /// * `GameOptions` - The seed, grid size and starting level.
+    pub fn get_options(&self) -> GameOptions {
+        self.options
+    }
+
+    /// Returns the current piece.
+    ///
+    /// # Returns
+    ///
+    /// * `&PhysicalPiece` - The current piece.
+    pub fn get_current(&self) -> &PhysicalPiece {
+        &self.current
+    }
+
+    /// Returns the next piece.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&Piece>` - The next piece, or None if a fixed queue ran out.
+    pub fn get_next(&self) -> Option<&Piece> {
+        self.next.as_ref()
+    }
+
+    /// Returns the pieces coming after the current one, next piece first.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The most pieces to return, up to `MAX_PREVIEWS`.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Piece>` - The coming pieces, fewer near the end of a fixed queue.
+    pub fn get_previews(&self, count: usize) -> Vec<Piece> {
+        self.next
+            .iter()
+            .chain(self.queue.iter())
+            .take(count.min(MAX_PREVIEWS))
+            .copied()
+            .collect()
+    }
+
+    /// Returns where the current piece would land if hard dropped.
+    ///
+    /// # Returns
+    ///
+    /// * `PhysicalPiece` - The landing position of the current piece.
+    pub fn get_ghost(&self) -> PhysicalPiece {
+        let mut ghost = self.current.clone();
+        loop {
+            let mut below = ghost.clone();
+            below.newton();
+            if self.collides(&below) {
+                return ghost;
+            }
+            ghost = below;
+        }
+    }
+
+    /// Returns the held piece.
+    ///
+    /// # Returns
+    ///
+    /// * `&Option<Piece>` - The held piece.
+    pub fn get_held(&self) -> &Option<Piece> {
+        &self.held
+    }
+
+    /// Returns the current score.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The current score.
+    pub fn get_score(&self) -> u32 {
+        self.score
+    }
+
+    /// Returns the number of lines cleared so far.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The cleared lines.
+    pub fn get_lines(&self) -> u32 {
+        self.lines
+    }
+
+    /// Returns the lines cleared by the last locked piece.
+    ///
+    /// # Returns
+    ///
+    /// * `LineClear` - The last line clear.
+    pub fn get_last_clear(&self) -> LineClear {
+        self.last_clear
+    }
+
+    /// Returns whether the current piece can be swapped with the held piece.
+    /// With an empty hold, the next piece must exist to take its place: the
+    /// last piece of a fixed queue cannot be held away.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if holding is allowed now, otherwise false.
+    pub fn can_hold(&self) -> bool {
+        self.hold_allowed && !self.has_held && (self.held.is_some() || self.next.is_some())
+    }
+
+    /// Returns the finesse statistics of the game.
+    ///
+    /// # Returns
+    ///
+    /// * `Finesse` - The finesse statistics.
+    pub fn get_finesse(&self) -> Finesse {
+        self.finesse
+    }
+
+    /// Sets what happens when a piece is placed with more inputs than needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `training` - The finesse training mode.
+    pub fn set_finesse_training(&mut self, training: FinesseTraining) {
+        self.finesse_training = training;
+    }
+
+    /// Returns what happens when a piece is placed with more inputs than needed.
+    ///
+    /// # Returns
+    ///
+    /// * `FinesseTraining` - The finesse training mode.
+    pub fn get_finesse_training(&self) -> FinesseTraining {
+        self.finesse_training
+    }
+
+    /// Sets whether locked pieces are checked for finesse. Checking searches
+    /// every placement of the piece, which copies played by the bot skip.
+    ///
+    /// # Arguments
+    ///
+    /// * `checked` - True to record finesse, false to leave it untouched.
+    pub fn set_finesse_checked(&mut self, checked: bool) {
+        self.finesse_checked = checked;
+    }
+
+    /// Sets whether finesse counts 180 turns among the shortest inputs. Off
+    /// by default, so that two quarter turns are not a fault for players
+    /// without a key for them.
+    ///
+    /// # Arguments
+    ///
+    /// * `half_turns` - True if the player has a key bound to `RotateHalf`.
+    pub fn set_finesse_half_turns(&mut self, half_turns: bool) {
+        self.finesse_half_turns = half_turns;
+    }
+
+    /// Returns whether finesse counts 180 turns among the shortest inputs.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if 180 turns are counted.
+    pub fn get_finesse_half_turns(&self) -> bool {
+        self.finesse_half_turns
+    }
+
+    /// Returns whether the game is over.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the game is over, otherwise false.
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    /// Returns the mode the game is played in.
+    ///
+    /// # Returns
+    ///
+    /// * `GameMode` - The game mode.
+    pub fn get_mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Returns the number of garbage lines left to clear.
+    ///
+    /// # Returns
+    ///
+    /// * `u16` - The remaining garbage lines.
+    pub fn get_garbage_remaining(&self) -> u16 {
+        self.garbage_remaining
+    }
+
+    /// Queues garbage lines sent by an opponent. They are raised when the
+    /// next piece locks without clearing lines, less the lines its clears
+    /// cancel.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The number of garbage lines received.
+    pub fn receive_garbage(&mut self, lines: u32) {
+        self.incoming_garbage += lines;
+    }
+
+    /// Returns the number of received garbage lines not raised yet.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The incoming garbage lines.
+    pub fn get_incoming_garbage(&self) -> u32 {
+        self.incoming_garbage
+    }
+
+    /// Takes the garbage lines sent by clears since the last call, to be
+    /// received by an opponent.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The garbage lines sent.
+    pub fn take_attack(&mut self) -> u32 {
+        std::mem::take(&mut self.outgoing_garbage)
+    }
+
+    /// Returns whether no piece is in play: full rows wait for the line clear
+    /// delay, or the next piece for the entry delay. Inputs other than
+    /// rotations and hold, kept for the next piece, are ignored meanwhile.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True between a lock and the spawn of the next piece.
+    pub fn is_between_pieces(&self) -> bool {
+        self.pending_clear.is_some() || self.pending_spawn.is_some()
+    }
+
+    /// Returns whether the objective of the game mode was reached.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the game was completed, otherwise false.
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// Returns the time the game lasted, if it is over.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Duration>` - The duration of the game, or None if still running.
+    pub fn get_finished_in(&self) -> Option<Duration> {
+        self.finished_in
+    }
+
+    /// Starts the clocks of the game from now: the time played and the frames
+    /// run. A game can be created well before play begins, on the title
+    /// screen, and that time is not played.
+    pub fn start_clock(&mut self) {
+        let now = Instant::now();
+        self.started = now;
+        self.time = now;
+        self.accumulator = Duration::ZERO;
+        self.frame = 0;
+    }
+
+    /// Returns the time played so far, stopping when the game is over.
+    ///
+    /// # Returns
+    ///
+    /// * `Duration` - The time played.
+    pub fn get_elapsed(&self) -> Duration {
+        self.finished_in
+            .unwrap_or_else(|| Instant::now().duration_since(self.started))
+    }
+
+    /// Returns the statistics of the game.
+    ///
+    /// # Returns
+    ///
+    /// * `&Stats` - The statistics.
+    pub fn get_stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns the events that happened since the last call, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Event>` - The events.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
     }
 
-    /// Returns true if a collision occurred
-    fn move_and_collide(&mut self, func: fn(&mut PhysicalPiece)) -> bool {
-        let mut test_piece = self.current.clone();
-        func(&mut test_piece);
-        if self.collides(&test_piece) {
-            true
-        } else {
-            func(&mut self.current);
-            false
-        }
+    /// Returns a number telling this game apart from every other game
+    /// created, for views to notice when they are given another game.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The id of the game, shared by its copies.
+    pub fn id(&self) -> u64 {
+        self.id
     }
 
-    fn collides(&self, piece: &PhysicalPiece) -> bool {
-        let shape = piece.get_shape();
-        let mut collision = false;
-        let mut s_i = 0;
-        while !collision && s_i < 4 {
-            let p = shape[s_i];
-            let p_x = piece.x + p.0 as i16;
-            let p_y = piece.y + p.1 as i16;
+    /// Returns the grid cells that changed since the last call. Every cell is
+    /// returned after the game is created.
+    ///
+    /// # Returns
+    ///
+    /// * `HashSet<(u16, u16)>` - The changed cells, as (x, y).
+    pub fn take_dirty_cells(&mut self) -> HashSet<(u16, u16)> {
+        std::mem::take(&mut self.dirty)
+    }
 
-            // Check if out of bounds
-            if p_x < 0 || p_x >= Game::GRID_WIDTH as i16 || p_y >= Game::GRID_HEIGHT as i16 {
-                collision = true;
-            } else if p_y >= 0 && self.grid[p_y as usize][p_x as usize].is_some() {
-                // Check if grid has some at p_x and p_y
-                collision = true;
-            }
+    /// Sets how held keys behave.
+    ///
+    /// # Arguments
+    ///
+    /// * `handling` - The auto shift and soft drop speeds.
+    pub fn set_handling(&mut self, handling: Handling) {
+        self.handling = handling;
+    }
 
-            s_i += 1;
-        }
+    /// Returns how held keys behave.
+    ///
+    /// # Returns
+    ///
+    /// * `Handling` - The auto shift and soft drop speeds.
+    pub fn get_handling(&self) -> Handling {
+        self.handling
+    }
 
-        collision
+    /// Sets the kicks tried when a 180 degree rotation is blocked.
+    ///
+    /// # Arguments
+    ///
+    /// * `kicks` - The kick table, `NO_KICKS` to turn only in place.
+    pub fn set_half_turn_kicks(&mut self, kicks: KickTable) {
+        self.half_turn_kicks = kicks;
     }
 
-    fn compute_score(&self, cleared: u8) -> u32 {
-        let score = match cleared {
-            0 => 0,
-            1 => 40,
-            2 => 100,
-            3 => 300,
-            4 => 1200,
-            _ => panic!("Invalid line number"),
-        };
+    /// Sets how long full rows stay on the grid before they are cleared.
+    /// During the delay the game waits and ignores inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - The line clear delay, zero to clear rows at once.
+    pub fn set_line_clear_delay(&mut self, delay: Duration) {
+        self.line_clear_delay = delay;
+    }
 
-        return score;
+    /// Sets how long the next piece waits to spawn after a lock, or after
+    /// the line clear delay when lines were cleared. Held shifts keep
+    /// charging meanwhile.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - The entry delay (ARE), zero to spawn at once.
+    pub fn set_entry_delay(&mut self, delay: Duration) {
+        self.entry_delay = delay;
     }
 
-    /// Returns the game grid.
+    /// Returns how long the next piece waits to spawn after a lock.
     ///
     /// # Returns
     ///
-    /// * `&[[Option<Color>; Game::GRID_WIDTH as usize]; Game::GRID_HEIGHT as usize]` - The game grid.
-    pub fn get_grid<'a>(
-        &'a self,
-    ) -> &'a [[Option<Color>; Game::GRID_WIDTH as usize]; Game::GRID_HEIGHT as usize] {
-        &self.grid
+    /// * `Duration` - The entry delay.
+    pub fn get_entry_delay(&self) -> Duration {
+        self.entry_delay
     }
 
-    /// Returns the current piece.
+    /// Returns how long full rows stay on the grid before they are cleared.
     ///
     /// # Returns
     ///
-    /// * `&PhysicalPiece` - The current piece.
-    pub fn get_current<'a>(&'a self) -> &'a PhysicalPiece {
-        &self.current
+    /// * `Duration` - The line clear delay.
+    pub fn get_line_clear_delay(&self) -> Duration {
+        self.line_clear_delay
     }
 
-    /// Returns the next piece.
+    fn record(&mut self, input: Input) {
+        if self.recording {
+            self.inputs.push((self.frame, input));
+        }
+    }
+
+    /// Sets whether inputs are recorded, for the game to be saved as a
+    /// replay. Games played by the bot are not recorded.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// * `&Piece` - The next piece.
-    pub fn get_next<'a>(&'a self) -> &'a Piece {
-        &self.next
+    /// * `recording` - True to record the inputs given from now on.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
     }
 
-    /// Returns the held piece.
+    /// Returns whether inputs are recorded.
     ///
     /// # Returns
     ///
-    /// * `&Option<Piece>` - The held piece.
-    pub fn get_held<'a>(&'a self) -> &'a Option<Piece> {
-        &self.held
+    /// * `bool` - True if the game records its inputs, otherwise false.
+    pub fn is_recording(&self) -> bool {
+        self.recording
     }
 
-    /// Returns the current score.
+    /// Returns every input recorded so far, with the frame it was given at.
     ///
     /// # Returns
     ///
-    /// * `u32` - The current score.
-    pub fn get_score(&self) -> u32 {
-        self.score
+    /// * `&[(u64, Input)]` - The inputs, oldest first.
+    pub fn get_inputs(&self) -> &[(u64, Input)] {
+        &self.inputs
     }
 
-    /// Returns whether the game is over.
+    /// Gives recorded inputs to the game, each one at the start of the frame
+    /// after the one it was recorded at, so that the game plays out as it
+    /// was recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - The recorded inputs, oldest first.
+    pub fn play_back(&mut self, inputs: &[(u64, Input)]) {
+        self.playback = inputs.iter().copied().collect();
+        self.replaying = true;
+    }
+
+    /// Returns whether the game replays recorded inputs.
     ///
     /// # Returns
     ///
-    /// * `bool` - True if the game is over, otherwise false.
-    pub fn is_game_over(&self) -> bool {
-        self.game_over
+    /// * `bool` - True for a replay, otherwise false.
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
     }
 }
 
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+/// Returns the number of frames a delay lasts, rounded up.
+fn delay_frames(delay: Duration) -> u32 {
+    delay.as_nanos().div_ceil(FRAME.as_nanos()) as u32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pieces::{Color, PhysicalPiece};
+    use crate::pieces::{PhysicalPiece, PieceKind, BLOCK_I};
 
     #[test]
     fn test_hold_piece() {
@@ -307,13 +1799,13 @@ mod tests {
     #[test]
     fn test_clear_lines() {
         let mut game = Game::new();
-        for x in 0..Game::GRID_WIDTH as usize {
-            game.grid[Game::GRID_HEIGHT as usize - 1][x] = Some(Color::RED);
+        for x in 0..Game::GRID_WIDTH {
+            game.board.set(x, Game::GRID_HEIGHT - 1, Some(PieceKind::Z));
         }
         let cleared = game.clear_lines();
         assert_eq!(cleared, 1);
         assert_eq!(
-            game.grid[Game::GRID_HEIGHT as usize - 1],
+            game.get_grid()[Game::GRID_HEIGHT as usize - 1],
             [None; Game::GRID_WIDTH as usize]
         );
     }
@@ -321,10 +1813,10 @@ mod tests {
     #[test]
     fn test_spawn_new_piece() {
         let mut game = Game::new();
-        let initial_next_piece = game.next;
+        let previews = game.get_previews(2);
         game.spawn_new();
-        assert_eq!(game.current.piece, initial_next_piece);
-        assert_ne!(game.next, initial_next_piece);
+        assert_eq!(game.current.piece, previews[0]);
+        assert_eq!(game.next, Some(previews[1]));
     }
 
     #[test]
@@ -338,8 +1830,10 @@ mod tests {
     #[test]
     fn test_game_over() {
         let mut game = Game::new();
-        for x in 0..Game::GRID_WIDTH as usize {
-            game.grid[Game::GRID_HEIGHT as usize - 1][x] = Some(Color::RED);
+        // The T piece spawns partly above the grid
+        game.current = Game::spawn_position(BLOCK_T, Game::GRID_WIDTH);
+        for x in 0..Game::GRID_WIDTH {
+            game.board.set(x, Game::GRID_HEIGHT - 1, Some(PieceKind::Z));
         }
         if game.boup() {
             game.game_over = true;
@@ -347,6 +1841,91 @@ mod tests {
         assert!(game.game_over);
     }
 
+    #[test]
+    fn test_no_input_after_game_over() {
+        let mut game = Game::with_options(GameOptions {
+            seed: 1,
+            ..GameOptions::default()
+        });
+        for _ in 0..100 {
+            game.press(Action::HardDrop);
+        }
+        assert!(game.is_game_over());
+
+        let grid = game.get_grid().clone();
+        let stats = *game.get_stats();
+        game.press(Action::HardDrop);
+        game.tick();
+        assert_eq!(game.get_grid(), &grid);
+        assert_eq!(game.get_stats(), &stats);
+    }
+
+    #[test]
+    fn test_options() {
+        let options = GameOptions {
+            seed: 42,
+            width: 6,
+            height: 12,
+            level: 99,
+        };
+        let a = Game::with_options(options);
+        let b = Game::with_options(options);
+        assert_eq!(a.get_previews(MAX_PREVIEWS), b.get_previews(MAX_PREVIEWS));
+        assert_eq!(a.current, b.current);
+        assert_eq!((a.width(), a.height()), (6, 12));
+        assert_eq!(a.current.x, (6 - a.current.piece.size() as i16) / 2);
+        assert_eq!(a.get_level(), MAX_LEVEL);
+        assert_eq!(a.gravity_interval(), 1);
+    }
+
+    #[test]
+    fn test_levels() {
+        let mut game = Game::new();
+        assert_eq!(game.get_level(), 1);
+        assert_eq!(game.gravity_interval(), GRAVITY_FRAMES);
+        game.lines = LINES_PER_LEVEL;
+        assert_eq!(game.get_level(), 2);
+        assert!(game.gravity_interval() < GRAVITY_FRAMES);
+        assert_eq!(game.compute_score(4), 2400);
+    }
+
+    #[test]
+    fn test_garbage() {
+        let height = Game::GRID_HEIGHT as usize;
+        let mut game = Game::new();
+        let piece = game.current.piece;
+        game.receive_garbage(3);
+        game.finish_lock(false, piece);
+        assert_eq!(game.get_incoming_garbage(), 0);
+        let grid = game.get_grid();
+        let hole = grid[height - 1].iter().position(Option::is_none);
+        for row in &grid[height - 3..] {
+            assert!(garbage::is_garbage_row(row));
+            assert_eq!(row.iter().position(Option::is_none), hole);
+        }
+        assert!(grid[height - 4].iter().all(Option::is_none));
+
+        // A triple sends two lines, the first cancelling the received one
+        let mut game = Game::new();
+        game.receive_garbage(1);
+        for y in height - 3..height {
+            game.board.set_row(
+                y as u16,
+                vec![Some(PieceKind::Z); Game::GRID_WIDTH as usize],
+            );
+        }
+        // Not a perfect clear
+        game.board.set(0, height as u16 - 4, Some(PieceKind::Z));
+        game.finish_lock(false, piece);
+        assert_eq!(game.get_incoming_garbage(), 0);
+        assert_eq!(game.take_attack(), 1);
+        assert_eq!(game.take_attack(), 0);
+        assert!(!game
+            .get_grid()
+            .iter()
+            .any(|row| garbage::is_garbage_row(row)));
+    }
+
     #[test]
     fn test_compute_score() {
         let game = Game::new();
@@ -356,4 +1935,593 @@ mod tests {
         assert_eq!(game.compute_score(3), 300);
         assert_eq!(game.compute_score(4), 1200);
     }
+
+    #[test]
+    fn test_dig_race() {
+        let mut game = Game::new_dig_race(3, 0.0);
+        assert_eq!(game.get_garbage_remaining(), 3);
+        let bottom = Game::GRID_HEIGHT - 1;
+        for x in 0..Game::GRID_WIDTH {
+            if game.board.get(x, bottom).is_none() {
+                game.board.set(x, bottom, Some(PieceKind::Z));
+            }
+        }
+        assert_eq!(game.clear_lines(), 1);
+        assert_eq!(game.get_garbage_remaining(), 2);
+        game.check_completed();
+        assert!(!game.is_game_over());
+
+        for y in bottom - 1..=bottom {
+            for x in 0..Game::GRID_WIDTH {
+                if game.board.get(x, y).is_none() {
+                    game.board.set(x, y, Some(PieceKind::Z));
+                }
+            }
+        }
+        assert_eq!(game.clear_lines(), 2);
+        game.check_completed();
+        assert!(game.is_game_over());
+        assert!(game.get_finished_in().is_some());
+    }
+
+    fn puzzle(source: &str) -> Puzzle {
+        crate::puzzle::parse_pack(source).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_puzzle_perfect_clear() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: PC\nqueue: O\nobjective: perfect-clear\ngrid:\nXXXXXXXX..\nXXXXXXXX..\n",
+        ));
+        assert_eq!(game.get_next(), None);
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.handle_input(' ');
+        game.tick();
+        assert!(game.last_clear.perfect_clear);
+        assert!(game.is_completed());
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn test_pentomino_clear() {
+        let pentomino = Piece::new(
+            PieceKind::Custom(0),
+            5,
+            &[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)],
+        );
+        let mut puzzle = Puzzle {
+            name: String::new(),
+            grid: vec![vec![None; 10]; 20],
+            queue: vec![pentomino; 2],
+            hold_allowed: true,
+            objective: Objective::Lines(100),
+        };
+        let column = Game::new_puzzle(&puzzle).get_current().cells()[0].x as usize;
+        for row in &mut puzzle.grid[14..] {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if x != column {
+                    *cell = Some(PieceKind::Garbage);
+                }
+            }
+        }
+        // Not a perfect clear
+        puzzle.grid[13][0] = Some(PieceKind::Garbage);
+
+        let mut game = Game::new_puzzle(&puzzle);
+        game.handle_input(' ');
+        game.tick();
+        assert_eq!(game.get_lines(), 5);
+        assert_eq!(game.get_score(), 1500);
+        assert_eq!(game.last_clear.attack(), 5);
+        assert_eq!(game.get_stats().clears, [0, 0, 0, 1]);
+        assert!(!game.is_game_over());
+    }
+
+    #[test]
+    fn test_puzzle_tspin_double() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: TSD\nqueue: T\nobjective: tspin 2\ngrid:\nXX...XXXXX\nXXX...XXXX\nXXXX.XXXXX\n",
+        ));
+        game.handle_input('x');
+        game.handle_input(' ');
+        game.handle_input('x');
+        game.tick();
+        assert_eq!(
+            game.last_clear,
+            LineClear {
+                lines: 2,
+                tspin: true,
+                perfect_clear: false,
+            }
+        );
+        assert!(game.is_completed());
+    }
+
+    #[test]
+    fn test_puzzle_out_of_pieces() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: Fail\nqueue: O\nhold: no\nobjective: lines 1\n",
+        ));
+        game.handle_input('h');
+        assert_eq!(game.get_held(), &None);
+        game.handle_input(' ');
+        game.tick();
+        assert!(game.is_game_over());
+        assert!(!game.is_completed());
+    }
+
+    #[test]
+    fn test_puzzle_hold_last_piece() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: PC\nqueue: OI\nobjective: lines 2\ngrid:\nXXXXXXXX..\nXXXXXXXX..\n",
+        ));
+        game.handle_input(' ');
+        game.tick();
+        assert_eq!(game.current.piece, BLOCK_I);
+        assert!(!game.can_hold());
+        assert!(game.apply(Action::Hold));
+        assert_eq!(game.get_held(), &None);
+        assert!(!game.is_game_over());
+    }
+
+    #[test]
+    fn test_events() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: PC\nqueue: OO\nobjective: lines 2\ngrid:\nXXXXXXXX..\nXXXXXXXX..\n",
+        ));
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.handle_input(' ');
+        game.tick();
+        let bottom = Game::GRID_HEIGHT - 1;
+        let cells: Vec<Vector2> = [(8, 18), (8, 19), (9, 18), (9, 19)]
+            .map(|(x, y)| Vector2::new(x, y))
+            .to_vec();
+        assert_eq!(
+            game.take_events(),
+            vec![
+                Event::HardDrop {
+                    cells: cells.clone(),
+                    distance: 19,
+                },
+                Event::Locked { cells },
+                Event::LinesCleared {
+                    rows: vec![bottom - 1, bottom],
+                },
+            ]
+        );
+        assert!(game.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_dirty_cells() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: PC\nqueue: OO\nobjective: lines 4\ngrid:\nXXXXXXXX..\n",
+        ));
+        let all = game.take_dirty_cells();
+        assert_eq!(all.len(), (Game::GRID_WIDTH * Game::GRID_HEIGHT) as usize);
+        assert!(game.take_dirty_cells().is_empty());
+
+        game.handle_input(' ');
+        game.tick();
+        let placed = HashSet::from([(4, 17), (5, 17), (4, 18), (5, 18)]);
+        assert_eq!(game.take_dirty_cells(), placed);
+
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.handle_input(' ');
+        game.tick();
+        assert_eq!(game.take_dirty_cells(), all);
+    }
+
+    #[test]
+    fn test_line_clear_delay() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: PC\nqueue: OO\nobjective: lines 4\ngrid:\nXXXXXXXX..\nXXXXXXXX..\n",
+        ));
+        game.set_line_clear_delay(FRAME * 2);
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.handle_input(' ');
+        game.tick();
+        assert_eq!(game.get_lines(), 0);
+        assert!(game.apply(Action::MoveLeft));
+
+        game.step();
+        assert_eq!(game.get_lines(), 0);
+        game.step();
+        assert_eq!(game.get_lines(), 2);
+        assert!(!game.apply(Action::MoveLeft));
+    }
+
+    #[test]
+    fn test_entry_delay() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: PC\nqueue: OOT\nobjective: lines 4\ngrid:\nXXXXXXXX..\nXXXXXXXX..\n",
+        ));
+        game.set_line_clear_delay(FRAME * 2);
+        game.set_entry_delay(FRAME * 3);
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.press(Action::HardDrop);
+        assert!(game.is_between_pieces());
+        assert!(game.apply(Action::MoveLeft));
+        game.step();
+        assert_eq!(game.get_lines(), 0);
+        for _ in 0..3 {
+            game.step();
+        }
+        assert_eq!(game.get_lines(), 2);
+        assert!(game.is_between_pieces());
+        game.step();
+        assert!(!game.is_between_pieces());
+        assert_eq!(game.current.piece, crate::pieces::BLOCK_O);
+        assert!(!game.apply(Action::MoveLeft));
+
+        // A shift held through the delay moves the next piece at once.
+        game.set_handling(Handling {
+            das: 10,
+            arr: 2,
+            soft_drop: 1,
+        });
+        game.set_entry_delay(FRAME * 20);
+        game.press(Action::HardDrop);
+        game.press(Action::MoveRight);
+        for _ in 0..20 {
+            game.step();
+        }
+        let spawned = Game::spawn_position(BLOCK_T, Game::GRID_WIDTH);
+        assert_eq!(game.current.piece, BLOCK_T);
+        assert_eq!(game.current.x, spawned.x);
+        game.step();
+        assert_eq!(game.current.x, spawned.x + 1);
+    }
+
+    #[test]
+    fn test_spawn_position() {
+        let spawned = |piece| Game::spawn_position(piece, Game::GRID_WIDTH).cells();
+        let columns = |cells: &[Vector2]| {
+            let xs = cells.iter().map(|cell| cell.x);
+            (xs.clone().min().unwrap(), xs.max().unwrap())
+        };
+        assert_eq!(columns(&spawned(BLOCK_I)), (3, 6));
+        assert_eq!(columns(&spawned(crate::pieces::BLOCK_O)), (4, 5));
+        for piece in crate::pieces::PIECES {
+            let cells = spawned(*piece);
+            assert_eq!(cells.iter().map(|cell| cell.y).max(), Some(0));
+            if piece.size() == 3 {
+                assert_eq!(columns(&cells), (3, 5));
+            }
+        }
+    }
+
+    #[test]
+    fn test_initial_rotation_and_hold() {
+        let source = "name: PC\nqueue: OTI\nobjective: lines 4\ngrid:\nXXXXXXXX..\nXXXXXXXX..\n";
+        let mut game = Game::new_puzzle(&puzzle(source));
+        game.set_line_clear_delay(FRAME * 2);
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.press(Action::HardDrop);
+        assert!(game.pending_clear.is_some());
+        assert!(!game.apply(Action::RotateRight));
+        game.step();
+        game.step();
+        assert_eq!(game.current.piece, BLOCK_T);
+        assert_eq!(game.current.rotation, 1);
+        assert_eq!(game.piece_start, game.current);
+
+        let mut game = Game::new_puzzle(&puzzle(source));
+        game.set_line_clear_delay(FRAME * 2);
+        for _ in 0..5 {
+            game.handle_input('d');
+        }
+        game.press(Action::HardDrop);
+        assert!(game.apply(Action::MoveLeft));
+        assert!(!game.apply(Action::Hold));
+        game.step();
+        game.step();
+        assert_eq!(game.held, Some(BLOCK_T));
+        assert_eq!(game.current.piece, BLOCK_I);
+        assert_eq!(game.current.rotation, 0);
+        assert!(!game.can_hold());
+    }
+
+    #[test]
+    fn test_gravity_and_lock_delay() {
+        let mut game = Game::new_puzzle(&puzzle("name: O\nqueue: OO\nobjective: lines 1\n"));
+        let y = game.current.y;
+        for _ in 0..GRAVITY_FRAMES {
+            game.step();
+        }
+        assert_eq!(game.current.y, y + 1);
+
+        game.apply(Action::HardDrop);
+        for _ in 0..LOCK_DELAY_FRAMES - 1 {
+            game.step();
+        }
+        assert_eq!(game.get_stats().pieces, 0);
+        // Moving restarts the lock delay
+        game.apply(Action::MoveLeft);
+        for _ in 0..LOCK_DELAY_FRAMES - 1 {
+            game.step();
+        }
+        assert_eq!(game.get_stats().pieces, 0);
+        game.step();
+        assert_eq!(game.get_stats().pieces, 1);
+    }
+
+    #[test]
+    fn test_delayed_auto_shift() {
+        let mut game = Game::new_puzzle(&puzzle("name: O\nqueue: O\nobjective: lines 1\n"));
+        let x = game.current.x;
+        game.press(Action::MoveRight);
+        assert_eq!(game.current.x, x + 1);
+        let Handling { das, arr, .. } = Handling::default();
+        for _ in 0..das - 1 {
+            game.step();
+        }
+        assert_eq!(game.current.x, x + 1);
+        game.step();
+        assert_eq!(game.current.x, x + 2);
+        for _ in 0..arr {
+            game.step();
+        }
+        assert_eq!(game.current.x, x + 3);
+
+        game.release(Action::MoveRight);
+        for _ in 0..das {
+            game.step();
+        }
+        assert_eq!(game.current.x, x + 3);
+
+        // Without auto repeat delay, the piece shifts to the wall at once
+        game.set_handling(Handling {
+            das: 1,
+            arr: 0,
+            soft_drop: 0,
+        });
+        game.press(Action::MoveLeft);
+        game.step();
+        assert_eq!(game.current.x, 0);
+    }
+
+    #[test]
+    fn test_previews_and_ghost() {
+        let game = Game::new();
+        assert_eq!(game.get_previews(9).len(), MAX_PREVIEWS);
+        assert_eq!(game.get_previews(1), vec![game.next.unwrap()]);
+
+        let game = Game::new_puzzle(&puzzle("name: O\nqueue: OIT\nobjective: lines 1\n"));
+        assert_eq!(game.get_previews(5), vec![BLOCK_I, BLOCK_T]);
+        let ghost = game.get_ghost();
+        assert_eq!(ghost.x, game.current.x);
+        assert_eq!(ghost.y, Game::GRID_HEIGHT as i16 - 2);
+    }
+
+    #[test]
+    fn test_reachable_placements() {
+        let mut game = Game::new();
+        game.current = Game::spawn_position(crate::pieces::BLOCK_O, Game::GRID_WIDTH);
+        let placements = game.reachable_placements();
+        assert_eq!(placements.len(), Game::GRID_WIDTH as usize - 1);
+        let straight = placements
+            .iter()
+            .find(|p| p.piece.x == game.current.x)
+            .unwrap();
+        assert_eq!(straight.inputs, vec![Action::HardDrop]);
+    }
+
+    #[test]
+    fn test_reachable_placements_spin() {
+        let mut game = Game::new_puzzle(&puzzle(
+            "name: TSD\nqueue: T\nobjective: tspin 2\ngrid:\nXX...XXXXX\nXXX...XXXX\nXXXX.XXXXX\n",
+        ));
+        let placements = game.reachable_placements();
+        let slot = placements
+            .iter()
+            .find(|p| p.piece.rotation == 2 && p.piece.x == 3 && p.piece.y == 17)
+            .unwrap();
+        assert_eq!(
+            slot.inputs,
+            vec![Action::RotateLeft, Action::HardDrop, Action::RotateLeft]
+        );
+        for &action in &slot.inputs {
+            assert!(!game.apply(action));
+        }
+        assert_eq!(game.current, slot.piece);
+    }
+
+    #[test]
+    fn test_finesse_cheapest_inputs() {
+        let mut game = Game::new();
+        game.board.set(2, 14, Some(PieceKind::Z));
+        game.board.set(1, 16, Some(PieceKind::Z));
+        game.current = Game::spawn_position(BLOCK_I, Game::GRID_WIDTH);
+        game.start_piece();
+        // Under the overhang in two shifts, soft dropping first, so four are
+        // a fault even though fewer inputs reach it with more shifts
+        game.apply(Action::MoveRight);
+        game.apply(Action::MoveLeft);
+        while game.current.cells()[0].y < 15 {
+            game.apply(Action::SoftDrop);
+        }
+        game.apply(Action::MoveLeft);
+        game.apply(Action::MoveLeft);
+        game.press(Action::HardDrop);
+        assert_eq!(game.board.get(1, 15), Some(PieceKind::I));
+        assert_eq!(game.get_finesse().faults, 1);
+    }
+
+    #[test]
+    fn test_finesse_shift_to_wall() {
+        let mut game = Game::new();
+        game.current = Game::spawn_position(crate::pieces::BLOCK_O, Game::GRID_WIDTH);
+        game.start_piece();
+        // Tapped to the wall instead of holding the shift once
+        for _ in 0..4 {
+            game.apply(Action::MoveLeft);
+        }
+        game.press(Action::HardDrop);
+        assert_eq!(game.get_finesse().faults, 1);
+
+        game.current = Game::spawn_position(crate::pieces::BLOCK_O, Game::GRID_WIDTH);
+        game.start_piece();
+        game.press(Action::MoveLeft);
+        while game.current.x > 0 {
+            game.step();
+        }
+        game.release(Action::MoveLeft);
+        game.press(Action::HardDrop);
+        assert_eq!(game.get_finesse().faults, 1);
+        assert_eq!(game.get_finesse().clean_pieces, 1);
+    }
+
+    #[test]
+    fn test_rotate_half() {
+        let mut game = Game::new();
+        let t = |x, y, rotation| PhysicalPiece {
+            x,
+            y,
+            rotation,
+            piece: BLOCK_T,
+        };
+
+        // In place, then kicked up off the floor
+        game.current = t(3, 5, 0);
+        assert!(!game.apply(Action::RotateHalf));
+        assert_eq!(game.current, t(3, 5, 2));
+        game.current = t(3, 18, 0);
+        assert!(!game.apply(Action::RotateHalf));
+        assert_eq!(game.current, t(3, 17, 2));
+        game.current = t(3, 18, 0);
+        game.set_half_turn_kicks(NO_KICKS);
+        assert!(game.apply(Action::RotateHalf));
+        assert_eq!(game.current, t(3, 18, 0));
+
+        // Turned into a slot with three corners blocked
+        for (x, y) in [(0, 19), (2, 19), (0, 17)] {
+            game.board.set(x, y, Some(PieceKind::Garbage));
+        }
+        game.current = t(0, 17, 0);
+        assert!(!game.apply(Action::RotateHalf));
+        assert_eq!(game.current, t(0, 17, 2));
+        assert!(game.is_tspin());
+    }
+
+    #[test]
+    fn test_finesse_fault() {
+        let mut game = Game::new();
+        game.current = Game::spawn_position(crate::pieces::BLOCK_O, Game::GRID_WIDTH);
+        game.start_piece();
+        game.handle_input('d');
+        game.handle_input('d');
+        game.handle_input(' ');
+        game.tick();
+        assert_eq!(game.get_finesse().faults, 0);
+
+        game.current = Game::spawn_position(crate::pieces::BLOCK_O, Game::GRID_WIDTH);
+        game.start_piece();
+        game.handle_input('d');
+        game.handle_input('q');
+        game.handle_input(' ');
+        game.tick();
+        let finesse = game.get_finesse();
+        assert_eq!(finesse.pieces, 2);
+        assert_eq!(finesse.clean_pieces, 1);
+        assert_eq!(finesse.faults, 1);
+    }
+
+    #[test]
+    fn test_finesse_half_turn() {
+        let mut game = Game::new();
+        for half_turns in [false, true] {
+            game.set_finesse_half_turns(half_turns);
+            game.current = Game::spawn_position(BLOCK_T, Game::GRID_WIDTH);
+            game.start_piece();
+            game.handle_input('z');
+            game.handle_input('z');
+            game.handle_input(' ');
+            game.tick();
+        }
+        // Two quarter turns are only a fault with a key for 180 turns
+        let finesse = game.get_finesse();
+        assert_eq!(finesse.pieces, 2);
+        assert_eq!(finesse.clean_pieces, 1);
+        assert_eq!(finesse.faults, 1);
+    }
+
+    #[test]
+    fn test_finesse_training_restart() {
+        let mut game = Game::new();
+        game.set_finesse_training(FinesseTraining::Restart);
+        game.current = Game::spawn_position(crate::pieces::BLOCK_O, Game::GRID_WIDTH);
+        game.start_piece();
+        game.handle_input('q');
+        game.handle_input('d');
+        game.handle_input(' ');
+        game.tick();
+        assert_eq!(game.current, game.piece_start);
+        assert!(game.board.is_empty());
+        assert_eq!(game.get_finesse().faults, 1);
+    }
+
+    #[test]
+    fn test_line_clear_attack() {
+        let clear = |lines, tspin, perfect_clear| LineClear {
+            lines,
+            tspin,
+            perfect_clear,
+        };
+        assert_eq!(clear(1, false, false).attack(), 0);
+        assert_eq!(clear(2, false, false).attack(), 1);
+        assert_eq!(clear(3, false, false).attack(), 2);
+        assert_eq!(clear(4, false, false).attack(), 4);
+        assert_eq!(clear(2, true, false).attack(), 4);
+        assert_eq!(clear(2, false, true).attack(), 11);
+        assert_eq!(clear(5, false, false).attack(), 5);
+    }
+
+    #[test]
+    fn test_stats_fed_by_engine() {
+        let mut game = Game::new();
+        game.handle_input('h');
+        game.handle_input('h');
+        game.handle_input(' ');
+        game.tick();
+        let stats = game.get_stats();
+        assert_eq!(stats.keys, 3);
+        assert_eq!(stats.holds, 1);
+        assert_eq!(stats.pieces, 1);
+        assert_eq!(stats.piece_counts.iter().sum::<u32>(), 1);
+    }
+
+    #[test]
+    fn test_start_clock() {
+        let mut game = Game::new();
+        std::thread::sleep(FRAME * 3);
+        game.start_clock();
+        assert!(game.get_elapsed() < FRAME * 3);
+        assert_eq!(game.frames_due(), 0);
+    }
+
+    #[test]
+    fn test_frames_due() {
+        let mut game = Game::new();
+        std::thread::sleep(FRAME * 3);
+        let frames = game.frames_due();
+        assert!((3..=MAX_FRAMES_PER_UPDATE).contains(&frames));
+
+        // A long stall is not caught up
+        std::thread::sleep(FRAME * (MAX_FRAMES_PER_UPDATE + 5));
+        assert_eq!(game.frames_due(), MAX_FRAMES_PER_UPDATE);
+        assert!(game.frames_due() < 3);
+    }
 }
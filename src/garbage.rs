@@ -0,0 +1,83 @@
+use crate::pieces::PieceKind;
+use rand::Rng;
+
+/// A single row of the game grid.
+pub type Row = Vec<Option<PieceKind>>;
+
+/// Generates garbage rows, each filled except for a single hole.
+///
+/// # Arguments
+///
+/// * `rng` - The random number generator used to place the holes.
+/// * `count` - The number of rows to generate.
+/// * `width` - The number of cells in a row.
+/// * `messiness` - The chance, between 0 and 1, that the hole moves to another
+///   column from one row to the next. 0 gives a straight well, 1 a new column
+///   on every row.
+///
+/// # Returns
+///
+/// * `Vec<Row>` - The generated rows, from top to bottom.
+pub fn generate_rows<R: Rng>(rng: &mut R, count: usize, width: usize, messiness: f32) -> Vec<Row> {
+    let messiness = messiness.clamp(0.0, 1.0);
+    let mut rows = Vec::with_capacity(count);
+    let mut hole = rng.gen_range(0..width);
+    for i in 0..count {
+        if i > 0 && rng.gen_bool(messiness as f64) {
+            // Pick a different column so that a change is always visible
+            hole = (hole + rng.gen_range(1..width)) % width;
+        }
+        let mut row = vec![Some(PieceKind::Garbage); width];
+        row[hole] = None;
+        rows.push(row);
+    }
+
+    rows
+}
+
+/// Returns whether the row contains garbage cells.
+///
+/// # Arguments
+///
+/// * `row` - The row to check.
+///
+/// # Returns
+///
+/// * `bool` - True if at least one cell is garbage, otherwise false.
+pub fn is_garbage_row(row: &[Option<PieceKind>]) -> bool {
+    row.contains(&Some(PieceKind::Garbage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_rows_single_hole() {
+        let mut rng = rand::thread_rng();
+        let rows = generate_rows(&mut rng, 8, 10, 0.5);
+        assert_eq!(rows.len(), 8);
+        for row in &rows {
+            assert_eq!(row.iter().filter(|cell| cell.is_none()).count(), 1);
+            assert!(is_garbage_row(row));
+        }
+    }
+
+    #[test]
+    fn test_generate_rows_messiness() {
+        let mut rng = rand::thread_rng();
+        let clean = generate_rows(&mut rng, 10, 10, 0.0);
+        let hole = clean[0].iter().position(|cell| cell.is_none());
+        for row in &clean {
+            assert_eq!(row.iter().position(|cell| cell.is_none()), hole);
+        }
+
+        let messy = generate_rows(&mut rng, 10, 10, 1.0);
+        for pair in messy.windows(2) {
+            assert_ne!(
+                pair[0].iter().position(|cell| cell.is_none()),
+                pair[1].iter().position(|cell| cell.is_none())
+            );
+        }
+    }
+}
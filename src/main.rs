@@ -1,61 +1,39 @@
-use game::Game;
-use slint::{SharedString, Timer};
-use std::{cell::RefCell, rc::Rc, time::Duration};
-
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "gui", target_arch = "wasm32"))]
 use wasm_bindgen::prelude::*;
 
-mod game;
-mod pieces;
+pub mod board;
+pub mod bot;
+pub mod cli;
+pub mod cpu;
+pub mod finesse;
+pub mod game;
+pub mod garbage;
+pub mod pieces;
+pub mod puzzle;
+pub mod replay;
+pub mod settings;
+pub mod skin;
+pub mod stats;
+mod storage;
+pub mod utils;
+pub mod versus;
+
+#[cfg(feature = "gui")]
+mod app;
+#[cfg(feature = "gui")]
 mod controller {
+    pub mod demo_controller;
     pub mod game_controller;
+    pub mod versus_controller;
 }
-use controller::*;
 
+#[cfg(feature = "gui")]
 pub mod ui {
     slint::include_modules!();
 }
-use ui::*;
 
+#[cfg(feature = "gui")]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn main() {
-    #[cfg(all(debug_assertions, target_arch = "wasm32"))]
-    console_error_panic_hook::set_once();
-
-    let ui = AppWindow::new().unwrap();
-    let game = Rc::new(RefCell::new(Game::new()));
-
-    let _game_controller = game_controller::setup(&ui, game.clone());
-
-    let game_handle = game.clone();
-    let ui_handle = ui.as_weak();
-    let game_update_timer = Timer::default();
-    let duration = Duration::from_millis(500);
-    game_update_timer.start(slint::TimerMode::Repeated, duration, {
-        move || {
-            if ui_handle.unwrap().global::<GameAdapter>().get_playing() {
-                game_handle.borrow_mut().update();
-            }
-        }
-    });
-
-    let ui_handle = ui.as_weak();
-    let game_handle = game.clone();
-    ui.global::<GameAdapter>().on_play_pressed(move || {
-        let ui = ui_handle.unwrap();
-        let game_adapter = ui.global::<GameAdapter>();
-        if game_adapter.get_game_over() {
-            game_handle.replace(Game::new());
-        }
-        game_adapter.set_playing(true);
-    });
-
-    let game_handle = game.clone();
-    ui.on_key_pressed(move |key_text: SharedString| {
-        let keycode = key_text.as_str().chars().nth(0).unwrap();
-        let mut game = game_handle.borrow_mut();
-        game.handle_input(keycode);
-    });
-
-    ui.run().unwrap();
+    app::run();
 }
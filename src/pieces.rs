@@ -1,30 +1,140 @@
+use crate::utils::Vector2;
 use std::clone::Clone;
 pub const PIECE_COUNT: usize = 7;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Color {
-    CYAN,
-    BLUE,
-    ORANGE,
-    YELLOW,
-    GREEN,
-    PURPLE,
-    RED,
+/// What a piece, or a cell of the grid, is. The colour it is drawn with is
+/// picked from its kind by the skin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    I,
+    J,
+    L,
+    O,
+    S,
+    T,
+    Z,
+    /// Cells of the garbage rows.
+    Garbage,
+    /// Pieces other than the seven tetrominoes, told apart by a number.
+    Custom(u8),
 }
 
+impl PieceKind {
+    /// The kinds with a colour of their own in skins, in order.
+    pub const COLORED: [PieceKind; 8] = [
+        PieceKind::I,
+        PieceKind::J,
+        PieceKind::L,
+        PieceKind::O,
+        PieceKind::S,
+        PieceKind::T,
+        PieceKind::Z,
+        PieceKind::Garbage,
+    ];
+
+    /// Returns the index of the colour of the kind in `COLORED`. Custom kinds
+    /// take the colours of the seven pieces in turn.
+    pub fn color_index(self) -> usize {
+        match self {
+            PieceKind::Custom(number) => number as usize % PIECE_COUNT,
+            kind => PieceKind::COLORED
+                .iter()
+                .position(|&colored| colored == kind)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Most cells a piece can have.
+pub const MAX_CELLS: usize = 8;
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Piece {
-    pub color: Color,
-    rotations: [[(u16, u16); 4]; 4],
+    pub kind: PieceKind,
+    /// Number of cells of the piece.
+    len: usize,
+    /// Side of the square box the piece rotates in.
+    size: u16,
+    /// Cells of each rotation, in the box. Only the first `len` are used.
+    rotations: [[Vector2; MAX_CELLS]; 4],
 }
 
 impl Piece {
-    pub fn get_shape<'a>(&'a self, rotation: usize) -> &'a [(u16, u16); 4] {
-        &self.rotations[rotation]
+    /// Creates a piece from the cells of its spawn rotation. The other
+    /// rotations are quarter turns clockwise around the centre of its box,
+    /// the pivot of the Super Rotation System.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - What the piece is.
+    /// * `size` - The side of the square box the piece rotates in.
+    /// * `cells` - The cells of the spawn rotation, as (x, y) in the box.
+    ///
+    /// # Returns
+    ///
+    /// * `Piece` - The piece with its four rotations.
+    pub const fn new(kind: PieceKind, size: u16, cells: &[(i16, i16)]) -> Piece {
+        assert!(!cells.is_empty() && cells.len() <= MAX_CELLS);
+        assert!(size as usize <= MAX_CELLS);
+        let mut rotations = [[Vector2::new(0, 0); MAX_CELLS]; 4];
+        let mut i = 0;
+        while i < cells.len() {
+            let (x, y) = cells[i];
+            assert!(x >= 0 && y >= 0 && x < size as i16 && y < size as i16);
+            rotations[0][i] = Vector2::new(x, y);
+            let mut rotation = 1;
+            while rotation < 4 {
+                let cell = rotations[rotation - 1][i];
+                rotations[rotation][i] = Vector2::new(size as i16 - 1 - cell.y, cell.x);
+                rotation += 1;
+            }
+            i += 1;
+        }
+
+        Piece {
+            kind,
+            len: cells.len(),
+            size,
+            rotations,
+        }
+    }
+
+    pub fn get_shape(&self, rotation: usize) -> &[Vector2] {
+        &self.rotations[rotation][..self.len]
+    }
+
+    /// Returns the smallest box holding the cells of a rotation.
+    ///
+    /// # Arguments
+    ///
+    /// * `rotation` - The rotation, from 0 to 3.
+    ///
+    /// # Returns
+    ///
+    /// * `(Vector2, Vector2)` - The top left cell of the box, and its width
+    ///   and height.
+    pub fn bounding_box(&self, rotation: usize) -> (Vector2, Vector2) {
+        let shape = self.get_shape(rotation);
+        let min = shape
+            .iter()
+            .fold(Vector2::new(i16::MAX, i16::MAX), |min, cell| {
+                Vector2::new(min.x.min(cell.x), min.y.min(cell.y))
+            });
+        let max = shape
+            .iter()
+            .fold(Vector2::new(i16::MIN, i16::MIN), |max, cell| {
+                Vector2::new(max.x.max(cell.x), max.y.max(cell.y))
+            });
+        (min, max.sub(min).add(Vector2::new(1, 1)))
+    }
+
+    /// Returns the side of the square box the piece rotates in.
+    pub fn size(&self) -> u16 {
+        self.size
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PhysicalPiece {
     pub x: i16,
     pub y: i16,
@@ -41,6 +151,10 @@ impl PhysicalPiece {
         self.rotation = (self.rotation + 4 - 1) % 4;
     }
 
+    pub fn rotate_half(&mut self) {
+        self.rotation = (self.rotation + 2) % 4;
+    }
+
     pub fn move_right(&mut self) {
         self.x += 1;
     }
@@ -53,12 +167,21 @@ impl PhysicalPiece {
         self.y += 1;
     }
 
-    pub fn get_shape(&self) -> &[(u16, u16); 4] {
+    pub fn get_shape(&self) -> &[Vector2] {
         self.piece.get_shape(self.rotation)
     }
 
+    /// Returns the grid positions of the cells of the piece.
+    pub fn cells(&self) -> Vec<Vector2> {
+        let position = Vector2::new(self.x, self.y);
+        self.get_shape()
+            .iter()
+            .map(|&cell| position.add(cell))
+            .collect()
+    }
+
     #[allow(dead_code)]
-    pub fn get_piece<'a>(&'a self) -> &'a Piece {
+    pub fn get_piece(&self) -> &Piece {
         &self.piece
     }
 }
@@ -67,80 +190,34 @@ pub static PIECES: [&Piece; PIECE_COUNT] = [
     &BLOCK_I, &BLOCK_J, &BLOCK_L, &BLOCK_O, &BLOCK_S, &BLOCK_T, &BLOCK_Z,
 ];
 
-pub static BLOCK_I: Piece = Piece {
-    color: Color::CYAN,
-    rotations: [
-        [(0, 1), (1, 1), (2, 1), (3, 1)],
-        [(2, 0), (2, 1), (2, 2), (2, 3)],
-        [(0, 2), (1, 2), (2, 2), (3, 2)],
-        [(1, 0), (1, 1), (1, 2), (1, 3)],
-    ],
-};
-
-pub static BLOCK_O: Piece = Piece {
-    color: Color::YELLOW,
-    rotations: [
-        [(0, 0), (0, 1), (1, 0), (1, 1)],
-        [(0, 0), (0, 1), (1, 0), (1, 1)],
-        [(0, 0), (0, 1), (1, 0), (1, 1)],
-        [(0, 0), (0, 1), (1, 0), (1, 1)],
-    ],
-};
-
-pub static BLOCK_T: Piece = Piece {
-    color: Color::PURPLE,
-    rotations: [
-        [(1, 0), (0, 1), (1, 1), (2, 1)],
-        [(1, 0), (1, 1), (1, 2), (2, 1)],
-        [(0, 1), (1, 1), (2, 1), (1, 2)],
-        [(1, 0), (1, 1), (1, 2), (0, 1)],
-    ],
-};
-
-pub static BLOCK_S: Piece = Piece {
-    color: Color::GREEN,
-    rotations: [
-        [(0, 1), (1, 1), (1, 0), (2, 0)],
-        [(1, 0), (1, 1), (2, 1), (2, 2)],
-        [(0, 2), (1, 2), (1, 1), (2, 1)],
-        [(0, 0), (0, 1), (1, 1), (1, 2)],
-    ],
-};
-
-pub static BLOCK_Z: Piece = Piece {
-    color: Color::RED,
-    rotations: [
-        [(0, 0), (1, 0), (1, 1), (2, 1)],
-        [(1, 2), (1, 1), (2, 1), (2, 0)],
-        [(0, 1), (1, 1), (1, 2), (2, 2)],
-        [(0, 2), (0, 1), (1, 1), (1, 0)],
-    ],
-};
-
-pub static BLOCK_J: Piece = Piece {
-    color: Color::BLUE,
-    rotations: [
-        [(0, 0), (0, 1), (1, 1), (2, 1)],
-        [(1, 2), (1, 1), (1, 0), (2, 0)],
-        [(0, 1), (1, 1), (2, 1), (2, 2)],
-        [(0, 2), (1, 2), (1, 1), (1, 0)],
-    ],
-};
-
-pub static BLOCK_L: Piece = Piece {
-    color: Color::ORANGE,
-    rotations: [
-        [(0, 1), (1, 1), (2, 1), (2, 0)],
-        [(1, 0), (1, 1), (1, 2), (2, 2)],
-        [(0, 2), (0, 1), (1, 1), (2, 1)],
-        [(0, 0), (1, 0), (1, 1), (1, 2)],
-    ],
-};
+pub static BLOCK_I: Piece = Piece::new(PieceKind::I, 4, &[(0, 1), (1, 1), (2, 1), (3, 1)]);
+
+pub static BLOCK_O: Piece = Piece::new(PieceKind::O, 2, &[(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+pub static BLOCK_T: Piece = Piece::new(PieceKind::T, 3, &[(1, 0), (0, 1), (1, 1), (2, 1)]);
+
+pub static BLOCK_S: Piece = Piece::new(PieceKind::S, 3, &[(0, 1), (1, 1), (1, 0), (2, 0)]);
+
+pub static BLOCK_Z: Piece = Piece::new(PieceKind::Z, 3, &[(0, 0), (1, 0), (1, 1), (2, 1)]);
+
+pub static BLOCK_J: Piece = Piece::new(PieceKind::J, 3, &[(0, 0), (0, 1), (1, 1), (2, 1)]);
+
+pub static BLOCK_L: Piece = Piece::new(PieceKind::L, 3, &[(0, 1), (1, 1), (2, 1), (2, 0)]);
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn cells(cells: &[(i16, i16)]) -> Vec<Vector2> {
+        cells.iter().map(|&(x, y)| Vector2::new(x, y)).collect()
+    }
+
+    fn sorted(shape: &[Vector2]) -> Vec<Vector2> {
+        let mut shape = shape.to_vec();
+        shape.sort();
+        shape
+    }
+
     #[test]
     fn test_rotate_right() {
         let mut piece = PhysicalPiece {
@@ -177,6 +254,20 @@ mod tests {
         assert_eq!(piece.rotation, 0);
     }
 
+    #[test]
+    fn test_rotate_half() {
+        let mut piece = PhysicalPiece {
+            x: 0,
+            y: 0,
+            rotation: 3,
+            piece: BLOCK_T,
+        };
+        piece.rotate_half();
+        assert_eq!(piece.rotation, 1);
+        piece.rotate_half();
+        assert_eq!(piece.rotation, 3);
+    }
+
     #[test]
     fn test_move_right() {
         let mut piece = PhysicalPiece {
@@ -227,13 +318,97 @@ mod tests {
             rotation: 0,
             piece: BLOCK_I,
         };
-        assert_eq!(piece.get_shape(), &[(0, 1), (1, 1), (2, 1), (3, 1)]);
+        assert_eq!(piece.get_shape(), &cells(&[(0, 1), (1, 1), (2, 1), (3, 1)]));
         let piece = PhysicalPiece {
             x: 0,
             y: 0,
             rotation: 1,
             piece: BLOCK_I,
         };
-        assert_eq!(piece.get_shape(), &[(2, 0), (2, 1), (2, 2), (2, 3)]);
+        assert_eq!(piece.get_shape(), &cells(&[(2, 0), (2, 1), (2, 2), (2, 3)]));
+    }
+
+    #[test]
+    fn test_cells() {
+        let piece = PhysicalPiece {
+            x: 3,
+            y: -1,
+            rotation: 0,
+            piece: BLOCK_I,
+        };
+        assert_eq!(piece.cells(), cells(&[(3, 0), (4, 0), (5, 0), (6, 0)]));
+    }
+
+    #[test]
+    fn test_generated_rotations() {
+        // The Super Rotation System states of the T and S pieces
+        let t = [
+            [(1, 0), (0, 1), (1, 1), (2, 1)],
+            [(1, 0), (1, 1), (1, 2), (2, 1)],
+            [(0, 1), (1, 1), (2, 1), (1, 2)],
+            [(1, 0), (1, 1), (1, 2), (0, 1)],
+        ];
+        let s = [
+            [(0, 1), (1, 1), (1, 0), (2, 0)],
+            [(1, 0), (1, 1), (2, 1), (2, 2)],
+            [(0, 2), (1, 2), (1, 1), (2, 1)],
+            [(0, 0), (0, 1), (1, 1), (1, 2)],
+        ];
+        for (piece, rotations) in [(BLOCK_T, t), (BLOCK_S, s)] {
+            for (rotation, expected) in rotations.iter().enumerate() {
+                assert_eq!(sorted(piece.get_shape(rotation)), sorted(&cells(expected)));
+            }
+        }
+        for rotation in 0..4 {
+            assert_eq!(
+                sorted(BLOCK_O.get_shape(rotation)),
+                sorted(BLOCK_O.get_shape(0))
+            );
+        }
+    }
+
+    #[test]
+    fn test_other_cell_counts() {
+        let tromino = Piece::new(PieceKind::Custom(0), 3, &[(0, 1), (1, 1), (2, 1)]);
+        assert_eq!(tromino.get_shape(1), &cells(&[(1, 0), (1, 1), (1, 2)]));
+        let pentomino = Piece::new(
+            PieceKind::Custom(1),
+            5,
+            &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 2)],
+        );
+        assert_eq!(pentomino.get_shape(0).len(), 5);
+        assert_eq!(
+            pentomino.get_shape(1),
+            &cells(&[(2, 0), (2, 1), (2, 2), (2, 3), (2, 4)])
+        );
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        assert_eq!(
+            BLOCK_I.bounding_box(0),
+            (Vector2::new(0, 1), Vector2::new(4, 1))
+        );
+        assert_eq!(
+            BLOCK_I.bounding_box(1),
+            (Vector2::new(2, 0), Vector2::new(1, 4))
+        );
+        assert_eq!(
+            BLOCK_O.bounding_box(0),
+            (Vector2::new(0, 0), Vector2::new(2, 2))
+        );
+        assert_eq!(
+            BLOCK_T.bounding_box(0),
+            (Vector2::new(0, 0), Vector2::new(3, 2))
+        );
+    }
+
+    #[test]
+    fn test_color_index() {
+        for (i, kind) in PieceKind::COLORED.into_iter().enumerate() {
+            assert_eq!(kind.color_index(), i);
+        }
+        assert_eq!(PieceKind::Custom(0).color_index(), 0);
+        assert_eq!(PieceKind::Custom(9).color_index(), 2);
     }
 }
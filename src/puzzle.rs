@@ -0,0 +1,356 @@
+use crate::game::{Game, Grid, LineClear};
+use crate::garbage::Row;
+use crate::pieces::{Piece, PieceKind, PIECES};
+use crate::storage;
+use std::fmt;
+
+/// The puzzles shipped with the game.
+pub const BUILTIN_PACK: &str = include_str!("../assets/puzzles/basics.txt");
+
+/// Name the user puzzle pack is stored under, next to the settings.
+pub const USER_PACK_NAME: &str = "puzzles.txt";
+
+/// Letters used for pieces in queues and grids, in the same order as `PIECES`.
+const PIECE_LETTERS: &str = "IJLOSTZ";
+
+/// What the player has to achieve to solve a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// Clear at least this many lines in total.
+    Lines(u32),
+    /// Clear exactly this many lines at once with a T-spin.
+    TSpin(u8),
+    /// Leave the grid empty after a line clear.
+    PerfectClear,
+}
+
+impl Objective {
+    /// Returns whether the objective is met after a piece locked.
+    ///
+    /// # Arguments
+    ///
+    /// * `clear` - The line clear caused by the last locked piece.
+    /// * `lines` - The total number of lines cleared so far.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the objective is met, otherwise false.
+    pub fn is_met(&self, clear: &LineClear, lines: u32) -> bool {
+        match *self {
+            Objective::Lines(target) => lines >= target,
+            Objective::TSpin(target) => clear.tspin && clear.lines == target,
+            Objective::PerfectClear => clear.perfect_clear,
+        }
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Objective::Lines(1) => write!(f, "Clear 1 line"),
+            Objective::Lines(n) => write!(f, "Clear {} lines", n),
+            Objective::TSpin(1) => write!(f, "T-spin single"),
+            Objective::TSpin(2) => write!(f, "T-spin double"),
+            Objective::TSpin(3) => write!(f, "T-spin triple"),
+            Objective::TSpin(n) => write!(f, "T-spin clearing {} lines", n),
+            Objective::PerfectClear => write!(f, "Perfect clear"),
+        }
+    }
+}
+
+/// A preset board with a fixed piece sequence and an objective.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub name: String,
+    pub grid: Grid,
+    pub queue: Vec<Piece>,
+    pub hold_allowed: bool,
+    pub objective: Objective,
+}
+
+/// An error found while reading a puzzle pack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns the built-in puzzles followed by the puzzles of the user pack.
+/// An invalid user pack is reported and ignored.
+pub fn load_all() -> Vec<Puzzle> {
+    let mut puzzles = parse_pack(BUILTIN_PACK).expect("the built-in puzzles are valid");
+    if let Some(source) = storage::read(USER_PACK_NAME) {
+        match parse_pack(&source) {
+            Ok(user_puzzles) => puzzles.extend(user_puzzles),
+            Err(error) => storage::report(&format!("Could not read {}: {}", USER_PACK_NAME, error)),
+        }
+    }
+
+    puzzles
+}
+
+/// Parses a puzzle pack.
+///
+/// A pack is a list of puzzles separated by `---` lines. Each puzzle has
+/// `name`, `queue`, `hold` and `objective` fields followed by a `grid:` line
+/// and the bottom rows of the grid, one line per row. Grid cells are `.` for
+/// empty, `X` for garbage or a piece letter. Lines starting with `#` are
+/// ignored.
+///
+/// ```text
+/// name: T-spin double
+/// queue: T
+/// hold: no
+/// objective: tspin 2
+/// grid:
+/// XX...XXXXX
+/// XXX...XXXX
+/// XXXX.XXXXX
+/// ```
+///
+/// # Arguments
+///
+/// * `source` - The content of the pack.
+///
+/// # Returns
+///
+/// * `Result<Vec<Puzzle>, ParseError>` - The puzzles, or the first error found.
+pub fn parse_pack(source: &str) -> Result<Vec<Puzzle>, ParseError> {
+    let mut puzzles = Vec::new();
+    let mut builder = PuzzleBuilder::default();
+    let mut last_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        let number = i + 1;
+        last_line = number;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "---" {
+            if !builder.is_empty() {
+                puzzles.push(builder.build(number)?);
+            }
+            builder = PuzzleBuilder::default();
+        } else if builder.in_grid {
+            builder.rows.push(parse_row(line, number)?);
+        } else if line == "grid:" {
+            builder.in_grid = true;
+        } else {
+            let (key, value) = line.split_once(':').ok_or_else(|| ParseError {
+                line: number,
+                message: format!("expected `key: value`, found `{}`", line),
+            })?;
+            builder.set(key.trim(), value.trim(), number)?;
+        }
+    }
+    if !builder.is_empty() {
+        puzzles.push(builder.build(last_line)?);
+    }
+
+    Ok(puzzles)
+}
+
+#[derive(Default)]
+struct PuzzleBuilder {
+    name: Option<String>,
+    queue: Option<Vec<Piece>>,
+    hold_allowed: Option<bool>,
+    objective: Option<Objective>,
+    rows: Vec<Row>,
+    in_grid: bool,
+}
+
+impl PuzzleBuilder {
+    fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.queue.is_none()
+            && self.hold_allowed.is_none()
+            && self.objective.is_none()
+            && !self.in_grid
+    }
+
+    fn set(&mut self, key: &str, value: &str, line: usize) -> Result<(), ParseError> {
+        let error = |message: String| ParseError { line, message };
+        match key {
+            "name" => self.name = Some(value.to_string()),
+            "queue" => {
+                let queue = value
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| piece_from_letter(c).ok_or(c))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|c| error(format!("unknown piece `{}`", c)))?;
+                if queue.is_empty() {
+                    return Err(error("the queue is empty".to_string()));
+                }
+                self.queue = Some(queue);
+            }
+            "hold" => {
+                self.hold_allowed = Some(match value {
+                    "yes" | "true" => true,
+                    "no" | "false" => false,
+                    _ => return Err(error(format!("expected yes or no, found `{}`", value))),
+                })
+            }
+            "objective" => self.objective = Some(parse_objective(value).map_err(error)?),
+            _ => return Err(error(format!("unknown field `{}`", key))),
+        }
+
+        Ok(())
+    }
+
+    fn build(self, line: usize) -> Result<Puzzle, ParseError> {
+        let missing = |field: &str| ParseError {
+            line,
+            message: format!("missing `{}`", field),
+        };
+        let height = Game::GRID_HEIGHT as usize;
+        if self.rows.len() > height {
+            return Err(ParseError {
+                line,
+                message: format!("the grid has more than {} rows", height),
+            });
+        }
+        let mut grid = vec![vec![None; Game::GRID_WIDTH as usize]; height];
+        let first = height - self.rows.len();
+        for (i, row) in self.rows.into_iter().enumerate() {
+            grid[first + i] = row;
+        }
+
+        Ok(Puzzle {
+            name: self.name.ok_or_else(|| missing("name"))?,
+            grid,
+            queue: self.queue.ok_or_else(|| missing("queue"))?,
+            hold_allowed: self.hold_allowed.unwrap_or(true),
+            objective: self.objective.ok_or_else(|| missing("objective"))?,
+        })
+    }
+}
+
+fn parse_objective(value: &str) -> Result<Objective, String> {
+    let mut words = value.split_whitespace();
+    let kind = words.next().unwrap_or_default();
+    let count = words.next().map(|n| n.parse::<u8>());
+    match (kind, count) {
+        ("lines", Some(Ok(n))) if n > 0 => Ok(Objective::Lines(n as u32)),
+        ("tspin", Some(Ok(n))) if (1..=3).contains(&n) => Ok(Objective::TSpin(n)),
+        ("perfect-clear", None) => Ok(Objective::PerfectClear),
+        _ => Err(format!("invalid objective `{}`", value)),
+    }
+}
+
+fn parse_row(line: &str, number: usize) -> Result<Row, ParseError> {
+    let width = Game::GRID_WIDTH as usize;
+    let cells: Vec<char> = line.chars().collect();
+    if cells.len() != width {
+        return Err(ParseError {
+            line: number,
+            message: format!("expected {} cells, found {}", width, cells.len()),
+        });
+    }
+    let mut row = vec![None; width];
+    for (x, c) in cells.into_iter().enumerate() {
+        row[x] = match c {
+            '.' => None,
+            'X' | 'G' => Some(PieceKind::Garbage),
+            _ => Some(
+                piece_from_letter(c)
+                    .ok_or_else(|| ParseError {
+                        line: number,
+                        message: format!("unknown cell `{}`", c),
+                    })?
+                    .kind,
+            ),
+        };
+    }
+
+    Ok(row)
+}
+
+fn piece_from_letter(letter: char) -> Option<Piece> {
+    PIECE_LETTERS
+        .find(letter.to_ascii_uppercase())
+        .map(|i| *PIECES[i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pieces::{BLOCK_I, BLOCK_T};
+
+    const PACK: &str = "
+# A comment
+name: First
+queue: TI
+hold: no
+objective: tspin 2
+grid:
+XX...XXXXX
+XXXX.XXXXX
+---
+name: Second
+queue: I
+objective: perfect-clear
+";
+
+    #[test]
+    fn test_parse_pack() {
+        let puzzles = parse_pack(PACK).unwrap();
+        assert_eq!(puzzles.len(), 2);
+
+        let first = &puzzles[0];
+        assert_eq!(first.name, "First");
+        assert_eq!(first.queue, vec![BLOCK_T, BLOCK_I]);
+        assert!(!first.hold_allowed);
+        assert_eq!(first.objective, Objective::TSpin(2));
+        let bottom = Game::GRID_HEIGHT as usize - 1;
+        assert_eq!(first.grid[bottom][4], None);
+        assert_eq!(first.grid[bottom][0], Some(PieceKind::Garbage));
+        assert_eq!(first.grid[bottom - 1][2], None);
+        assert_eq!(first.grid[bottom - 2], [None; Game::GRID_WIDTH as usize]);
+
+        let second = &puzzles[1];
+        assert!(second.hold_allowed);
+        assert_eq!(second.objective, Objective::PerfectClear);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_pack("name: A\nqueue: TQ\n").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = parse_pack("name: A\nqueue: T\nobjective: lines 1\ngrid:\nXX\n").unwrap_err();
+        assert_eq!(error.line, 5);
+
+        let error = parse_pack("name: A\nqueue: T\n").unwrap_err();
+        assert_eq!(error.message, "missing `objective`");
+    }
+
+    #[test]
+    fn test_builtin_pack() {
+        let puzzles = parse_pack(BUILTIN_PACK).unwrap();
+        assert!(!puzzles.is_empty());
+    }
+
+    #[test]
+    fn test_objective_is_met() {
+        let tsd = LineClear {
+            lines: 2,
+            tspin: true,
+            perfect_clear: false,
+        };
+        assert!(Objective::TSpin(2).is_met(&tsd, 2));
+        assert!(!Objective::TSpin(1).is_met(&tsd, 2));
+        assert!(Objective::Lines(2).is_met(&tsd, 2));
+        assert!(!Objective::Lines(3).is_met(&tsd, 2));
+        assert!(!Objective::PerfectClear.is_met(&tsd, 2));
+    }
+}
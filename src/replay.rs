@@ -0,0 +1,333 @@
+//! Recorded games: the options a game started with and every input given to
+//! it, enough to play the game again exactly as it went.
+
+use crate::finesse::FinesseTraining;
+use crate::game::{Action, Game, GameMode, GameOptions, Handling, Input};
+use crate::puzzle::ParseError;
+use crate::storage;
+use std::time::Duration;
+
+/// Version of the replay format, written to every replay.
+const VERSION: u32 = 3;
+
+/// Name the replay of the last game is stored under, next to the settings.
+pub const LAST_REPLAY_NAME: &str = "last-replay.txt";
+
+/// A recorded game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub options: GameOptions,
+    /// Marathon or dig race, puzzles are not recorded.
+    pub mode: GameMode,
+    pub handling: Handling,
+    pub line_clear_delay: Duration,
+    pub entry_delay: Duration,
+    pub finesse_training: FinesseTraining,
+    pub finesse_half_turns: bool,
+    /// The inputs with the frame they were given at, oldest first.
+    pub inputs: Vec<(u64, Input)>,
+}
+
+impl Replay {
+    /// Records a game, to be replayed with `start`.
+    ///
+    /// # Arguments
+    ///
+    /// * `game` - The game to record.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Replay>` - The replay, or None for a puzzle or a game that
+    ///   was not recording.
+    pub fn record(game: &Game) -> Option<Replay> {
+        if !game.is_recording() || matches!(game.get_mode(), GameMode::Puzzle { .. }) {
+            return None;
+        }
+
+        Some(Replay {
+            options: game.get_options(),
+            mode: game.get_mode(),
+            handling: game.get_handling(),
+            line_clear_delay: game.get_line_clear_delay(),
+            entry_delay: game.get_entry_delay(),
+            finesse_training: game.get_finesse_training(),
+            finesse_half_turns: game.get_finesse_half_turns(),
+            inputs: game.get_inputs().to_vec(),
+        })
+    }
+
+    /// Creates a game that plays the recorded inputs as it runs.
+    pub fn start(&self) -> Game {
+        let mut game = match self.mode {
+            GameMode::DigRace { rows, messiness } => {
+                Game::new_dig_race_with_options(self.options, rows, messiness)
+            }
+            _ => Game::with_options(self.options),
+        };
+        game.set_handling(self.handling);
+        game.set_line_clear_delay(self.line_clear_delay);
+        game.set_entry_delay(self.entry_delay);
+        game.set_finesse_training(self.finesse_training);
+        game.set_finesse_half_turns(self.finesse_half_turns);
+        game.play_back(&self.inputs);
+        game
+    }
+
+    /// Parses a replay written by `serialize`: `key = value` lines followed
+    /// by an `inputs:` line and one `frame kind action` line per input.
+    ///
+    /// ```text
+    /// version = 1
+    /// seed = 42
+    /// mode = marathon
+    /// ...
+    /// inputs:
+    /// 12 press move_left
+    /// 20 release move_left
+    /// ```
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The content of the replay.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Replay, ParseError>` - The replay, or the first error found.
+    pub fn parse(source: &str) -> Result<Replay, ParseError> {
+        let mut replay = Replay {
+            options: GameOptions {
+                seed: 0,
+                ..GameOptions::default()
+            },
+            mode: GameMode::Marathon,
+            handling: Handling::default(),
+            line_clear_delay: Duration::ZERO,
+            entry_delay: Duration::ZERO,
+            finesse_training: FinesseTraining::Off,
+            finesse_half_turns: false,
+            inputs: Vec::new(),
+        };
+        let mut seed = None;
+        let mut in_inputs = false;
+        for (i, line) in source.lines().enumerate() {
+            let number = i + 1;
+            let error = |message: String| ParseError {
+                line: number,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if in_inputs {
+                replay.inputs.push(parse_input(line).map_err(error)?);
+                continue;
+            }
+            if line == "inputs:" {
+                in_inputs = true;
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `key = value`, found `{}`", line)))?;
+            let value = value.trim();
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| error(format!("expected a number, found `{}`", value)))
+            };
+            match key.trim() {
+                "version" => {
+                    if number(value)? > VERSION as u64 {
+                        return Err(error("the replay was saved by a newer version".into()));
+                    }
+                }
+                "seed" => seed = Some(number(value)?),
+                "width" => replay.options.width = number(value)? as u16,
+                "height" => replay.options.height = number(value)? as u16,
+                "level" => replay.options.level = number(value)? as u32,
+                "mode" => replay.mode = parse_mode(value).map_err(error)?,
+                "das" => replay.handling.das = number(value)? as u32,
+                "arr" => replay.handling.arr = number(value)? as u32,
+                "soft_drop" => replay.handling.soft_drop = number(value)? as u32,
+                "line_clear_delay" => {
+                    replay.line_clear_delay = Duration::from_millis(number(value)?)
+                }
+                "entry_delay" => replay.entry_delay = Duration::from_millis(number(value)?),
+                "finesse_training" => {
+                    replay.finesse_training = match value {
+                        "off" => FinesseTraining::Off,
+                        "flash" => FinesseTraining::Flash,
+                        "restart" => FinesseTraining::Restart,
+                        _ => return Err(error(format!("unknown finesse training `{}`", value))),
+                    }
+                }
+                "finesse_half_turns" => {
+                    replay.finesse_half_turns = value
+                        .parse()
+                        .map_err(|_| error(format!("expected true or false, found `{}`", value)))?
+                }
+                key => return Err(error(format!("unknown field `{}`", key))),
+            }
+        }
+        replay.options.seed = seed.ok_or_else(|| ParseError {
+            line: source.lines().count(),
+            message: "missing `seed`".into(),
+        })?;
+
+        Ok(replay)
+    }
+
+    /// Returns the replay as text, readable by `parse`.
+    pub fn serialize(&self) -> String {
+        let mode = match self.mode {
+            GameMode::DigRace { rows, messiness } => format!("dig_race {} {}", rows, messiness),
+            _ => "marathon".to_string(),
+        };
+        let finesse_training = match self.finesse_training {
+            FinesseTraining::Off => "off",
+            FinesseTraining::Flash => "flash",
+            FinesseTraining::Restart => "restart",
+        };
+        let mut text = format!(
+            "version = {}\nseed = {}\nwidth = {}\nheight = {}\nlevel = {}\nmode = {}\n",
+            VERSION,
+            self.options.seed,
+            self.options.width,
+            self.options.height,
+            self.options.level,
+            mode
+        );
+        text += &format!(
+            "das = {}\narr = {}\nsoft_drop = {}\nline_clear_delay = {}\nentry_delay = {}\n",
+            self.handling.das,
+            self.handling.arr,
+            self.handling.soft_drop,
+            self.line_clear_delay.as_millis(),
+            self.entry_delay.as_millis()
+        );
+        text += &format!(
+            "finesse_training = {}\nfinesse_half_turns = {}\n",
+            finesse_training, self.finesse_half_turns
+        );
+        text += "inputs:\n";
+        for (frame, input) in &self.inputs {
+            let (kind, action) = match input {
+                Input::Press(action) => ("press", action),
+                Input::Release(action) => ("release", action),
+                Input::Apply(action) => ("apply", action),
+            };
+            text += &format!("{} {} {}\n", frame, kind, action.name());
+        }
+
+        text
+    }
+
+    /// Stores the replay as the replay of the last game.
+    pub fn save_last(&self) -> std::io::Result<()> {
+        storage::write(LAST_REPLAY_NAME, &self.serialize())
+    }
+}
+
+fn parse_mode(value: &str) -> Result<GameMode, String> {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    match words[..] {
+        ["marathon"] => Ok(GameMode::Marathon),
+        ["dig_race", rows, messiness] => match (rows.parse(), messiness.parse()) {
+            (Ok(rows), Ok(messiness)) => Ok(GameMode::DigRace { rows, messiness }),
+            _ => Err(format!("invalid dig race `{}`", value)),
+        },
+        _ => Err(format!("unknown mode `{}`", value)),
+    }
+}
+
+fn parse_input(line: &str) -> Result<(u64, Input), String> {
+    let invalid = || {
+        format!(
+            "expected `frame press|release|apply action`, found `{}`",
+            line
+        )
+    };
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let [frame, kind, action] = words[..] else {
+        return Err(invalid());
+    };
+    let frame = frame.parse().map_err(|_| invalid())?;
+    let action = Action::from_name(action).ok_or_else(invalid)?;
+    let input = match kind {
+        "press" => Input::Press(action),
+        "release" => Input::Release(action),
+        "apply" => Input::Apply(action),
+        _ => return Err(invalid()),
+    };
+
+    Ok((frame, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_plays_out_the_same() {
+        let options = GameOptions {
+            seed: 7,
+            width: 8,
+            height: 16,
+            level: 3,
+        };
+        let mut game = Game::new_dig_race_with_options(options, 4, 0.5);
+        game.set_recording(true);
+        game.set_finesse_half_turns(true);
+        let inputs = [
+            Action::MoveLeft,
+            Action::RotateRight,
+            Action::HardDrop,
+            Action::Hold,
+            Action::SoftDrop,
+        ];
+        for (i, action) in inputs.into_iter().cycle().take(60).enumerate() {
+            if game.is_game_over() {
+                break;
+            }
+            game.press(action);
+            for _ in 0..i % 7 {
+                game.step();
+            }
+            game.release(action);
+            game.step();
+        }
+
+        let replay = Replay::parse(&Replay::record(&game).unwrap().serialize()).unwrap();
+        assert!(replay.finesse_half_turns);
+        let mut replayed = replay.start();
+        replayed.set_recording(true);
+        assert!(replayed.is_replaying());
+        while replayed.get_inputs().len() < game.get_inputs().len() && !replayed.is_game_over() {
+            replayed.step();
+        }
+        assert_eq!(replayed.get_grid(), game.get_grid());
+        assert_eq!(replayed.get_score(), game.get_score());
+        assert_eq!(replayed.get_inputs(), game.get_inputs());
+    }
+
+    #[test]
+    fn test_unrecorded_game() {
+        let mut game = Game::new();
+        game.apply(Action::MoveLeft);
+        assert!(game.get_inputs().is_empty());
+        assert_eq!(Replay::record(&game), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = Replay::parse("version = 1\n").unwrap_err();
+        assert_eq!(error.message, "missing `seed`");
+
+        let error = Replay::parse("seed = 1\ninputs:\n3 press fly\n").unwrap_err();
+        assert_eq!(error.line, 3);
+
+        let error = Replay::parse("seed = 1\nmode = puzzle\n").unwrap_err();
+        assert_eq!(error.line, 2);
+    }
+}
@@ -0,0 +1,488 @@
+use crate::{
+    game::{Action, Handling, MAX_PREVIEWS},
+    skin::Skin,
+    storage,
+};
+use std::{fs, io, path::Path, str::FromStr};
+
+/// Name the settings are stored under.
+const STORAGE_NAME: &str = "settings";
+/// Version of the settings format, written to the stored settings.
+const VERSION: u32 = 1;
+
+/// The colour theme of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeMode {
+    /// Light or dark, following the system colour scheme.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl ThemeMode {
+    /// Returns the theme for the mode index used by the Slint `Theme` global.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - 0 for system, 1 for light, 2 for dark.
+    ///
+    /// # Returns
+    ///
+    /// * `ThemeMode` - The theme, System for an unknown index.
+    pub fn from_index(index: i32) -> ThemeMode {
+        match index {
+            1 => ThemeMode::Light,
+            2 => ThemeMode::Dark,
+            _ => ThemeMode::System,
+        }
+    }
+
+    /// Returns the mode index used by the Slint `Theme` global.
+    pub fn index(self) -> i32 {
+        match self {
+            ThemeMode::System => 0,
+            ThemeMode::Light => 1,
+            ThemeMode::Dark => 2,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ThemeMode::System => "system",
+            ThemeMode::Light => "light",
+            ThemeMode::Dark => "dark",
+        }
+    }
+
+    /// Returns the theme with a name used in the settings file.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - `system`, `light` or `dark`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<ThemeMode>` - The theme, or None for an unknown name.
+    pub fn from_name(name: &str) -> Option<ThemeMode> {
+        match name {
+            "system" => Some(ThemeMode::System),
+            "light" => Some(ThemeMode::Light),
+            "dark" => Some(ThemeMode::Dark),
+            _ => None,
+        }
+    }
+}
+
+/// Actions that can be bound to keys, in the order shown in the settings.
+pub const BOUND_ACTIONS: [Action; 8] = [
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::RotateRight,
+    Action::RotateLeft,
+    Action::RotateHalf,
+    Action::SoftDrop,
+    Action::HardDrop,
+    Action::Hold,
+];
+
+/// Keys bound by default, mapped to actions by `Action::from_key`.
+const DEFAULT_KEYS: &str = "\u{F702}q\u{F703}d\u{F700}zcxas h";
+
+/// Keys of each player in local versus by default, in the order of
+/// `BOUND_ACTIONS`: the left of the keyboard for player 1, the arrows for
+/// player 2.
+const VERSUS_KEYS: [[&str; BOUND_ACTIONS.len()]; 2] = [
+    ["a", "d", "w", "e", "r", "s", " ", "q"],
+    [
+        "\u{F702}", "\u{F703}", "\u{F700}", ".", ";", "\u{F701}", "/", ",",
+    ],
+];
+
+/// Names of the keys without a printable character.
+const KEY_NAMES: [(char, &str); 5] = [
+    ('\u{F702}', "Left"),
+    ('\u{F703}', "Right"),
+    ('\u{F700}', "Up"),
+    ('\u{F701}', "Down"),
+    (' ', "Space"),
+];
+
+/// The keys bound to each action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    /// Keys of each action, in the order of `BOUND_ACTIONS`.
+    keys: [Vec<char>; BOUND_ACTIONS.len()],
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = Bindings {
+            keys: Default::default(),
+        };
+        for key in DEFAULT_KEYS.chars() {
+            if let Some(action) = Action::from_key(key) {
+                bindings.keys[action_index(action)].push(key);
+            }
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    /// Returns the default bindings of a player in local versus.
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - 0 or 1.
+    ///
+    /// # Returns
+    ///
+    /// * `Bindings` - The bindings of the player.
+    pub fn versus(player: usize) -> Bindings {
+        Bindings {
+            keys: VERSUS_KEYS[player].map(|keys| keys.chars().collect()),
+        }
+    }
+
+    /// Returns the action bound to a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The character of the key.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Action>` - The bound action, or None if the key is unbound.
+    pub fn action(&self, key: char) -> Option<Action> {
+        let key = key.to_ascii_lowercase();
+        BOUND_ACTIONS
+            .iter()
+            .zip(&self.keys)
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds a key to an action, replacing the keys of the action. The key
+    /// is unbound from any other action.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action to bind.
+    /// * `key` - The character of the key.
+    pub fn bind(&mut self, action: Action, key: char) {
+        let key = key.to_ascii_lowercase();
+        for keys in &mut self.keys {
+            keys.retain(|&k| k != key);
+        }
+        self.keys[action_index(action)] = vec![key];
+    }
+
+    /// Returns whether any key is bound to an action.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - True if the action has a key.
+    pub fn is_bound(&self, action: Action) -> bool {
+        !self.keys[action_index(action)].is_empty()
+    }
+
+    /// Returns the names of the keys bound to an action, for display.
+    pub fn label(&self, action: Action) -> String {
+        self.keys[action_index(action)]
+            .iter()
+            .map(|&key| key_name(key))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn action_index(action: Action) -> usize {
+    BOUND_ACTIONS
+        .iter()
+        .position(|&a| a == action)
+        .expect("every action can be bound")
+}
+
+fn key_name(key: char) -> String {
+    KEY_NAMES
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map_or_else(|| key.to_string(), |(_, name)| name.to_string())
+}
+
+fn key_from_name(name: &str) -> Option<char> {
+    if let Some((key, _)) = KEY_NAMES.iter().find(|(_, n)| *n == name) {
+        return Some(*key);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) if !key.is_control() => Some(key),
+        _ => None,
+    }
+}
+
+/// User settings, kept between sessions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub theme: ThemeMode,
+    /// Name of the block skin.
+    pub skin: String,
+    /// Whether blocks show the letter of their piece.
+    pub patterns: bool,
+    /// How long full rows stay on the grid before they are cleared, in ms.
+    pub line_clear_delay: u32,
+    /// How long the next piece waits to spawn after a lock, in ms.
+    pub entry_delay: u32,
+    pub handling: Handling,
+    /// Whether the landing position of the current piece is shown.
+    pub ghost: bool,
+    /// Whether the empty cells of the grid are outlined.
+    pub grid_lines: bool,
+    /// Number of next pieces shown, from 1 to `MAX_PREVIEWS`.
+    pub preview_count: u32,
+    pub bindings: Bindings,
+    /// Keys of each player in local versus.
+    pub versus_bindings: [Bindings; 2],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: ThemeMode::default(),
+            skin: Skin::default().name,
+            patterns: false,
+            line_clear_delay: 0,
+            entry_delay: 0,
+            handling: Handling::default(),
+            ghost: true,
+            grid_lines: false,
+            preview_count: 1,
+            bindings: Bindings::default(),
+            versus_bindings: [Bindings::versus(0), Bindings::versus(1)],
+        }
+    }
+}
+
+impl Settings {
+    /// Parses settings from `key = value` lines. Unknown keys and invalid
+    /// values are ignored and keep their default, so files written by older
+    /// or newer versions still load.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The stored settings.
+    ///
+    /// # Returns
+    ///
+    /// * `Settings` - The parsed settings.
+    pub fn parse(text: &str) -> Settings {
+        let mut settings = Settings::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "version" => {
+                    if value.parse::<u32>().is_ok_and(|version| version > VERSION) {
+                        storage::report("Settings were saved by a newer version");
+                    }
+                }
+                "theme" => {
+                    if let Some(theme) = ThemeMode::from_name(value) {
+                        settings.theme = theme;
+                    }
+                }
+                "skin" if !value.is_empty() => settings.skin = value.to_string(),
+                "patterns" => parse_into(&mut settings.patterns, value),
+                "line_clear_delay" => parse_into(&mut settings.line_clear_delay, value),
+                "entry_delay" => parse_into(&mut settings.entry_delay, value),
+                "das" => parse_into(&mut settings.handling.das, value),
+                "arr" => parse_into(&mut settings.handling.arr, value),
+                "soft_drop" => parse_into(&mut settings.handling.soft_drop, value),
+                "ghost" => parse_into(&mut settings.ghost, value),
+                "grid_lines" => parse_into(&mut settings.grid_lines, value),
+                "preview_count" => {
+                    parse_into(&mut settings.preview_count, value);
+                    settings.preview_count = settings.preview_count.clamp(1, MAX_PREVIEWS as u32);
+                }
+                key => {
+                    let Some((bindings, action)) = settings.bindings_of_key(key) else {
+                        continue;
+                    };
+                    let keys: Option<Vec<char>> =
+                        value.split_whitespace().map(key_from_name).collect();
+                    if let (Some(action), Some(keys)) = (Action::from_name(action), keys) {
+                        bindings.keys[action_index(action)] = keys;
+                    }
+                }
+            }
+        }
+
+        settings
+    }
+
+    /// Returns the settings as `key = value` lines, readable by `parse`.
+    pub fn serialize(&self) -> String {
+        let mut text = format!(
+            "version = {}\ntheme = {}\nskin = {}\npatterns = {}\nline_clear_delay = {}\n",
+            VERSION,
+            self.theme.name(),
+            self.skin,
+            self.patterns,
+            self.line_clear_delay
+        );
+        text += &format!(
+            "entry_delay = {}\ndas = {}\narr = {}\nsoft_drop = {}\n",
+            self.entry_delay, self.handling.das, self.handling.arr, self.handling.soft_drop
+        );
+        text += &format!(
+            "ghost = {}\ngrid_lines = {}\npreview_count = {}\n",
+            self.ghost, self.grid_lines, self.preview_count
+        );
+        let players = [
+            ("p1.", &self.versus_bindings[0]),
+            ("p2.", &self.versus_bindings[1]),
+        ];
+        for (prefix, bindings) in [("", &self.bindings)].into_iter().chain(players) {
+            for action in BOUND_ACTIONS {
+                text += &format!(
+                    "{}bind.{} = {}\n",
+                    prefix,
+                    action.name(),
+                    bindings.label(action)
+                );
+            }
+        }
+
+        text
+    }
+
+    /// Returns the bindings a `bind.<action>` key sets, `p1.` and `p2.`
+    /// prefixes standing for the players of local versus, with the name of
+    /// the action.
+    fn bindings_of_key<'a>(&mut self, key: &'a str) -> Option<(&mut Bindings, &'a str)> {
+        let (bindings, key) = match key.split_once('.') {
+            Some(("p1", key)) => (&mut self.versus_bindings[0], key),
+            Some(("p2", key)) => (&mut self.versus_bindings[1], key),
+            _ => (&mut self.bindings, key),
+        };
+        key.strip_prefix("bind.").map(|action| (bindings, action))
+    }
+
+    /// Loads the stored settings, or the defaults if none are stored.
+    pub fn load() -> Settings {
+        storage::read(STORAGE_NAME)
+            .map(|text| Settings::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Stores the settings for the next session.
+    pub fn save(&self) -> io::Result<()> {
+        storage::write(STORAGE_NAME, &self.serialize())
+    }
+
+    /// Loads settings from a file instead of the stored settings, or the
+    /// defaults if the file does not exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The settings file.
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Settings>` - The settings, or the error reading the file.
+    pub fn load_file(path: &Path) -> io::Result<Settings> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Settings::parse(&text)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Saves the settings to a file instead of the stored settings.
+    pub fn save_file(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+}
+
+fn parse_into<T: FromStr>(field: &mut T, value: &str) {
+    if let Ok(parsed) = value.parse() {
+        *field = parsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings {
+            theme: ThemeMode::Dark,
+            skin: "Outline".to_string(),
+            patterns: true,
+            line_clear_delay: 200,
+            entry_delay: 100,
+            handling: Handling {
+                das: 8,
+                arr: 0,
+                soft_drop: 1,
+            },
+            ghost: false,
+            grid_lines: true,
+            preview_count: 5,
+            bindings: Bindings::default(),
+            versus_bindings: [Bindings::versus(0), Bindings::versus(1)],
+        };
+        settings.bindings.bind(Action::HardDrop, 'w');
+        settings.versus_bindings[1].bind(Action::Hold, 'm');
+        assert_eq!(Settings::parse(&settings.serialize()), settings);
+    }
+
+    #[test]
+    fn test_unversioned_settings() {
+        let settings = Settings::parse("theme = light\nskin = Flat\n");
+        assert_eq!(settings.theme, ThemeMode::Light);
+        assert_eq!(settings.skin, "Flat");
+        assert_eq!(settings.handling, Handling::default());
+    }
+
+    #[test]
+    fn test_bindings() {
+        let mut bindings = Bindings::default();
+        for key in DEFAULT_KEYS.chars() {
+            assert_eq!(bindings.action(key), Action::from_key(key));
+        }
+        assert_eq!(bindings.label(Action::MoveLeft), "Left q");
+
+        bindings.bind(Action::Hold, 'q');
+        assert_eq!(bindings.action('Q'), Some(Action::Hold));
+        assert_eq!(bindings.label(Action::MoveLeft), "Left");
+
+        assert!(bindings.is_bound(Action::RotateHalf));
+        bindings.bind(Action::Hold, 'a');
+        assert!(!bindings.is_bound(Action::RotateHalf));
+
+        assert_eq!(Bindings::versus(0).action(' '), Some(Action::HardDrop));
+        assert_eq!(Bindings::versus(1).label(Action::SoftDrop), "Down");
+        let settings = Settings::parse("p2.bind.hard_drop = Space\n");
+        assert_eq!(settings.versus_bindings[1].label(Action::HardDrop), "Space");
+        assert_eq!(settings.bindings, Bindings::default());
+    }
+
+    #[test]
+    fn test_parse_ignores_invalid() {
+        let settings = Settings::parse("# comment\nunknown = 1\ntheme = purple\n");
+        assert_eq!(settings, Settings::default());
+        assert_eq!(
+            ThemeMode::from_index(ThemeMode::Light.index()),
+            ThemeMode::Light
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use crate::pieces::PieceKind;
+use crate::puzzle::ParseError;
+use crate::storage;
+
+/// The skins shipped with the game.
+pub const BUILTIN_PACK: &str = include_str!("../assets/skins/builtin.txt");
+
+/// Name the user skin pack is stored under, next to the settings.
+pub const USER_PACK_NAME: &str = "skins.txt";
+
+/// Letters used for colours in skin packs, in the same order as
+/// `PieceKind::COLORED`: the seven pieces followed by garbage.
+const COLOR_LETTERS: &str = "IJLOSTZX";
+const COLOR_COUNT: usize = 8;
+
+/// A colour as red, green and blue components.
+pub type Rgb = (u8, u8, u8);
+
+/// How blocks are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockStyle {
+    /// A plain square of colour.
+    Flat,
+    /// A square with a darker border.
+    #[default]
+    Bevelled,
+    /// A coloured border around a faint fill.
+    Outline,
+}
+
+impl BlockStyle {
+    /// Returns the style index used by the Slint `BlockSkin` global.
+    pub fn index(self) -> i32 {
+        match self {
+            BlockStyle::Flat => 0,
+            BlockStyle::Bevelled => 1,
+            BlockStyle::Outline => 2,
+        }
+    }
+}
+
+/// The look of the blocks: a colour for every piece and for garbage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    pub name: String,
+    pub style: BlockStyle,
+    /// Corner radius, relative to the block size.
+    pub radius: f32,
+    colors: [Rgb; COLOR_COUNT],
+}
+
+impl Default for Skin {
+    fn default() -> Self {
+        Skin {
+            name: "Classic".to_string(),
+            style: BlockStyle::Bevelled,
+            radius: 0.25,
+            colors: [
+                (82, 177, 252),
+                (60, 118, 181),
+                (255, 92, 27),
+                (251, 206, 5),
+                (67, 213, 97),
+                (164, 105, 184),
+                (255, 1, 39),
+                (130, 130, 130),
+            ],
+        }
+    }
+}
+
+impl Skin {
+    /// Returns the colour of the blocks of a piece.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the piece, or garbage.
+    ///
+    /// # Returns
+    ///
+    /// * `Rgb` - The colour to draw the blocks with.
+    pub fn rgb(&self, kind: PieceKind) -> Rgb {
+        self.colors[kind.color_index()]
+    }
+}
+
+/// Returns the built-in skins followed by the skins of the user pack.
+/// An invalid user pack is reported and ignored.
+pub fn load_all() -> Vec<Skin> {
+    let mut skins = parse_pack(BUILTIN_PACK).expect("the built-in skins are valid");
+    if let Some(source) = storage::read(USER_PACK_NAME) {
+        match parse_pack(&source) {
+            Ok(user_skins) => skins.extend(user_skins),
+            Err(error) => storage::report(&format!("Could not read {}: {}", USER_PACK_NAME, error)),
+        }
+    }
+
+    skins
+}
+
+/// Parses a skin pack.
+///
+/// A pack is a list of skins separated by `---` lines, in the same format as
+/// puzzle packs. Each skin has a `name`, a `style` (`flat`, `bevelled` or
+/// `outline`), an optional `radius` and a `#RRGGBB` colour for every piece
+/// letter and for garbage (`X`). Lines starting with `#` are ignored.
+///
+/// ```text
+/// name: Classic
+/// style: bevelled
+/// radius: 0.25
+/// I: #52B1FC
+/// ...
+/// X: #828282
+/// ```
+///
+/// # Arguments
+///
+/// * `source` - The content of the pack.
+///
+/// # Returns
+///
+/// * `Result<Vec<Skin>, ParseError>` - The skins, or the first error found.
+pub fn parse_pack(source: &str) -> Result<Vec<Skin>, ParseError> {
+    let mut skins = Vec::new();
+    let mut builder = SkinBuilder::default();
+    let mut last_line = 0;
+    for (i, line) in source.lines().enumerate() {
+        let number = i + 1;
+        last_line = number;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "---" {
+            if !builder.is_empty() {
+                skins.push(builder.build(number)?);
+            }
+            builder = SkinBuilder::default();
+        } else {
+            let (key, value) = line.split_once(':').ok_or_else(|| ParseError {
+                line: number,
+                message: format!("expected `key: value`, found `{}`", line),
+            })?;
+            builder.set(key.trim(), value.trim(), number)?;
+        }
+    }
+    if !builder.is_empty() {
+        skins.push(builder.build(last_line)?);
+    }
+
+    Ok(skins)
+}
+
+#[derive(Default)]
+struct SkinBuilder {
+    name: Option<String>,
+    style: Option<BlockStyle>,
+    radius: Option<f32>,
+    colors: [Option<Rgb>; COLOR_COUNT],
+}
+
+impl SkinBuilder {
+    fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.style.is_none()
+            && self.radius.is_none()
+            && self.colors.iter().all(Option::is_none)
+    }
+
+    fn set(&mut self, key: &str, value: &str, line: usize) -> Result<(), ParseError> {
+        let error = |message: String| ParseError { line, message };
+        match key {
+            "name" => self.name = Some(value.to_string()),
+            "style" => {
+                self.style = Some(match value {
+                    "flat" => BlockStyle::Flat,
+                    "bevelled" => BlockStyle::Bevelled,
+                    "outline" => BlockStyle::Outline,
+                    _ => return Err(error(format!("unknown style `{}`", value))),
+                })
+            }
+            "radius" => {
+                let radius = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|r| (0.0..=0.5).contains(r))
+                    .ok_or_else(|| {
+                        error(format!(
+                            "expected a radius from 0 to 0.5, found `{}`",
+                            value
+                        ))
+                    })?;
+                self.radius = Some(radius);
+            }
+            _ => {
+                let index = key
+                    .chars()
+                    .next()
+                    .filter(|_| key.len() == 1)
+                    .and_then(|c| COLOR_LETTERS.find(c.to_ascii_uppercase()))
+                    .ok_or_else(|| error(format!("unknown field `{}`", key)))?;
+                self.colors[index] = Some(parse_rgb(value).map_err(error)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build(self, line: usize) -> Result<Skin, ParseError> {
+        let missing = |field: &str| ParseError {
+            line,
+            message: format!("missing `{}`", field),
+        };
+        let mut colors = [(0, 0, 0); COLOR_COUNT];
+        for (i, color) in self.colors.iter().enumerate() {
+            colors[i] = color.ok_or_else(|| missing(&COLOR_LETTERS[i..i + 1]))?;
+        }
+
+        Ok(Skin {
+            name: self.name.ok_or_else(|| missing("name"))?,
+            style: self.style.unwrap_or_default(),
+            radius: self.radius.unwrap_or(0.0),
+            colors,
+        })
+    }
+}
+
+fn parse_rgb(value: &str) -> Result<Rgb, String> {
+    let invalid = || format!("expected a `#RRGGBB` colour, found `{}`", value);
+    let hex = value
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())
+        .ok_or_else(invalid)?;
+    let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+
+    Ok((component(0)?, component(2)?, component(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_pack() {
+        let skins = parse_pack(BUILTIN_PACK).unwrap();
+        assert_eq!(skins[0], Skin::default());
+        assert!(skins.iter().any(|skin| skin.style == BlockStyle::Outline));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = parse_pack("name: A\nstyle: shiny\n").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = parse_pack("name: A\nI: #12345G\n").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = parse_pack("name: A\nI: #000000\n").unwrap_err();
+        assert_eq!(error.message, "missing `J`");
+    }
+}
@@ -0,0 +1,150 @@
+use crate::game::LineClear;
+use crate::pieces::{Piece, PIECES, PIECE_COUNT};
+use std::time::Duration;
+
+/// Statistics of a game, fed by the engine as pieces are placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    pub pieces: u32,
+    pub keys: u32,
+    pub holds: u32,
+    pub attack: u32,
+    /// Clears without a spin, indexed by the number of lines minus one. Clears
+    /// of more than four lines, made by larger pieces, count as tetrises.
+    pub clears: [u32; 4],
+    /// T-spin clears, indexed by the number of lines minus one, up to three.
+    pub tspin_clears: [u32; 3],
+    pub perfect_clears: u32,
+    /// Placed pieces, in the same order as `PIECES`.
+    pub piece_counts: [u32; PIECE_COUNT],
+}
+
+impl Stats {
+    /// Records a key press.
+    pub fn record_key(&mut self) {
+        self.keys += 1;
+    }
+
+    /// Records a successful hold.
+    pub fn record_hold(&mut self) {
+        self.holds += 1;
+    }
+
+    /// Records a locked piece and the lines it cleared.
+    ///
+    /// # Arguments
+    ///
+    /// * `piece` - The locked piece.
+    /// * `clear` - The lines cleared by the piece.
+    pub fn record_lock(&mut self, piece: &Piece, clear: &LineClear) {
+        self.pieces += 1;
+        if let Some(index) = PIECES.iter().position(|p| *p == piece) {
+            self.piece_counts[index] += 1;
+        }
+        if clear.lines > 0 {
+            let index = clear.lines.min(4) as usize - 1;
+            if clear.tspin {
+                self.tspin_clears[index.min(2)] += 1;
+            } else {
+                self.clears[index] += 1;
+            }
+        }
+        if clear.perfect_clear {
+            self.perfect_clears += 1;
+        }
+        self.attack += clear.attack();
+    }
+
+    /// Returns the pieces placed per second.
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed` - The time played.
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - The pieces per second.
+    pub fn pps(&self, elapsed: Duration) -> f32 {
+        per(self.pieces, elapsed.as_secs_f32())
+    }
+
+    /// Returns the attack sent per minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed` - The time played.
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - The attack per minute.
+    pub fn apm(&self, elapsed: Duration) -> f32 {
+        per(self.attack, elapsed.as_secs_f32() / 60.0)
+    }
+
+    /// Returns the keys pressed per placed piece.
+    ///
+    /// # Returns
+    ///
+    /// * `f32` - The keys per piece.
+    pub fn kpp(&self) -> f32 {
+        per(self.keys, self.pieces as f32)
+    }
+}
+
+fn per(count: u32, amount: f32) -> f32 {
+    if amount > 0.0 {
+        count as f32 / amount
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pieces::{BLOCK_I, BLOCK_T};
+
+    #[test]
+    fn test_record_lock() {
+        let mut stats = Stats::default();
+        stats.record_lock(&BLOCK_I, &LineClear::default());
+        stats.record_lock(
+            &BLOCK_I,
+            &LineClear {
+                lines: 4,
+                tspin: false,
+                perfect_clear: true,
+            },
+        );
+        stats.record_lock(
+            &BLOCK_T,
+            &LineClear {
+                lines: 2,
+                tspin: true,
+                perfect_clear: false,
+            },
+        );
+        assert_eq!(stats.pieces, 3);
+        assert_eq!(stats.piece_counts[0], 2);
+        assert_eq!(stats.piece_counts[5], 1);
+        assert_eq!(stats.clears, [0, 0, 0, 1]);
+        assert_eq!(stats.tspin_clears, [0, 1, 0]);
+        assert_eq!(stats.perfect_clears, 1);
+        assert_eq!(stats.attack, 14 + 4);
+    }
+
+    #[test]
+    fn test_rates() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.kpp(), 0.0);
+        assert_eq!(stats.pps(Duration::ZERO), 0.0);
+        for _ in 0..6 {
+            stats.record_key();
+        }
+        stats.pieces = 3;
+        stats.attack = 4;
+        assert_eq!(stats.kpp(), 2.0);
+        assert_eq!(stats.pps(Duration::from_secs(2)), 1.5);
+        assert_eq!(stats.apm(Duration::from_secs(30)), 8.0);
+    }
+}
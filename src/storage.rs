@@ -0,0 +1,94 @@
+//! Small text files kept between sessions: files in the user configuration
+//! directory on native targets, `localStorage` entries on the web.
+
+use std::io;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{env, fs, io, path::PathBuf};
+
+    fn path(name: &str) -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .or_else(|| env::var_os("APPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("tetris-slint").join(name))
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        fs::read_to_string(path(name)?).ok()
+    }
+
+    pub fn write(name: &str, text: &str) -> io::Result<()> {
+        let path = path(name).ok_or_else(|| io::Error::other("no config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, text)
+    }
+
+    pub fn report(message: &str) {
+        eprintln!("{}", message);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use std::io;
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(name: &str) -> String {
+        format!("tetris-slint-{}", name)
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub fn write(name: &str, text: &str) -> io::Result<()> {
+        local_storage()
+            .ok_or_else(|| io::Error::other("no local storage"))?
+            .set_item(&key(name), text)
+            .map_err(|_| io::Error::other("could not write local storage"))
+    }
+
+    pub fn report(message: &str) {
+        web_sys::console::warn_1(&message.into());
+    }
+}
+
+/// Reads a stored text.
+///
+/// # Arguments
+///
+/// * `name` - The name the text is stored under.
+///
+/// # Returns
+///
+/// * `Option<String>` - The text, or None if nothing could be read.
+pub fn read(name: &str) -> Option<String> {
+    backend::read(name)
+}
+
+/// Stores a text, replacing the previous one.
+///
+/// # Arguments
+///
+/// * `name` - The name to store the text under.
+/// * `text` - The text to store.
+pub fn write(name: &str, text: &str) -> io::Result<()> {
+    backend::write(name, text)
+}
+
+/// Reports a problem with the stored texts where the player can find it:
+/// on the standard error natively, in the browser console on the web.
+///
+/// # Arguments
+///
+/// * `message` - The problem to report.
+pub fn report(message: &str) {
+    backend::report(message)
+}
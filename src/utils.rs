@@ -1,27 +1,41 @@
+/// A position or offset on the grid, `x` to the right and `y` down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Vector2 {
-    x: i32,
-    y: i32
+    pub x: i16,
+    pub y: i16,
 }
 
 impl Vector2 {
-    fn new(x: i32, y: i32) -> Self {
-        Vector2 {x, y}
+    pub const fn new(x: i16, y: i16) -> Self {
+        Vector2 { x, y }
     }
 }
 
 impl Vector2 {
-    pub fn add(self, other: Self) -> Self {
+    pub const fn add(self, other: Self) -> Self {
         Vector2 {
             x: self.x + other.x,
-            y: self.y + other.y
+            y: self.y + other.y,
         }
     }
 
-    pub fn sub(self, other: Self) -> Self {
+    pub const fn sub(self, other: Self) -> Self {
         Vector2 {
             x: self.x - other.x,
-            y: self.y - other.y
+            y: self.y - other.y,
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_sub() {
+        let a = Vector2::new(3, -1);
+        let b = Vector2::new(1, 2);
+        assert_eq!(a.add(b), Vector2::new(4, 1));
+        assert_eq!(a.add(b).sub(b), a);
+    }
+}
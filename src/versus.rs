@@ -0,0 +1,287 @@
+//! Local versus: two games played side by side on one keyboard, or against
+//! the computer, each sending the garbage of its clears to the other, until
+//! one of them tops out.
+
+use crate::cpu::{CpuPlayer, Difficulty};
+use crate::game::{Game, GameOptions, Handling};
+use std::time::Duration;
+
+/// How a round ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The player, 0 or 1, whose opponent topped out.
+    Winner(usize),
+    /// Both players topped out on the same frame.
+    Draw,
+}
+
+/// A match of rounds between two players.
+pub struct Versus {
+    games: [Game; 2],
+    /// Rounds won by each player.
+    wins: [u32; 2],
+    /// Whether the outcome of the current round was counted in `wins`.
+    counted: bool,
+    /// The computer playing player 2, if any.
+    cpu: Option<CpuPlayer>,
+}
+
+impl Versus {
+    /// Starts a match with its first round.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of both games. They share the seed, so both
+    ///   players get the same pieces.
+    ///
+    /// # Returns
+    ///
+    /// * `Versus` - A new match.
+    pub fn new(options: GameOptions) -> Versus {
+        Versus {
+            games: [Game::with_options(options), Game::with_options(options)],
+            wins: [0; 2],
+            counted: false,
+            cpu: None,
+        }
+    }
+
+    /// Starts a match against the computer, playing player 2.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of both games.
+    /// * `difficulty` - How well the computer plays.
+    ///
+    /// # Returns
+    ///
+    /// * `Versus` - A new match.
+    pub fn against_cpu(options: GameOptions, difficulty: Difficulty) -> Versus {
+        Versus {
+            cpu: Some(CpuPlayer::new(difficulty, options.seed)),
+            ..Versus::new(options)
+        }
+    }
+
+    /// Starts the next round of the match, keeping the wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options of both games.
+    pub fn next_round(&mut self, options: GameOptions) {
+        self.games = [Game::with_options(options), Game::with_options(options)];
+        self.counted = false;
+        if let Some(cpu) = &mut self.cpu {
+            cpu.reset();
+        }
+    }
+
+    /// Sets the handling, line clear delay and entry delay of both games.
+    pub fn configure(
+        &mut self,
+        handling: Handling,
+        line_clear_delay: Duration,
+        entry_delay: Duration,
+    ) {
+        for game in &mut self.games {
+            game.set_handling(handling);
+            game.set_line_clear_delay(line_clear_delay);
+            game.set_entry_delay(entry_delay);
+        }
+    }
+
+    /// Runs both games for the time elapsed since the last call, one `step`
+    /// per frame due. Nothing runs once the round is over.
+    pub fn update(&mut self) {
+        // Both games follow the clock of player 1
+        for _ in 0..self.games[0].frames_due() {
+            self.step();
+        }
+        self.count_outcome();
+    }
+
+    /// Advances both games by one frame, the computer acting first, and
+    /// exchanges their garbage.
+    pub fn step(&mut self) {
+        if self.outcome().is_none() {
+            if let Some(cpu) = &mut self.cpu {
+                cpu.act(&mut self.games[1]);
+            }
+            for game in &mut self.games {
+                game.step();
+            }
+            let attacks = self.games.each_mut().map(Game::take_attack);
+            self.games[0].receive_garbage(attacks[1]);
+            self.games[1].receive_garbage(attacks[0]);
+        }
+        self.count_outcome();
+    }
+
+    /// Counts the outcome of the round once, when it ends. A round can also
+    /// end on an input, between two frames.
+    fn count_outcome(&mut self) {
+        if let (Some(outcome), false) = (self.outcome(), self.counted) {
+            if let Outcome::Winner(player) = outcome {
+                self.wins[player] += 1;
+            }
+            self.counted = true;
+        }
+    }
+
+    /// Returns how the round ended.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Outcome>` - The outcome, or None while both players play.
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.games.each_ref().map(Game::is_game_over) {
+            [false, false] => None,
+            [true, true] => Some(Outcome::Draw),
+            [false, true] => Some(Outcome::Winner(0)),
+            [true, false] => Some(Outcome::Winner(1)),
+        }
+    }
+
+    /// Returns the game of a player.
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - 0 or 1.
+    pub fn game(&self, player: usize) -> &Game {
+        &self.games[player]
+    }
+
+    /// Returns the game of a player, to give it inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - 0 or 1.
+    pub fn game_mut(&mut self, player: usize) -> &mut Game {
+        &mut self.games[player]
+    }
+
+    /// Returns whether player 2 is the computer.
+    pub fn has_cpu(&self) -> bool {
+        self.cpu.is_some()
+    }
+
+    /// Returns the rounds won by each player.
+    pub fn get_wins(&self) -> [u32; 2] {
+        self.wins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+    use crate::pieces::{PieceKind, BLOCK_O};
+    use crate::puzzle::{Objective, Puzzle};
+
+    fn options() -> GameOptions {
+        GameOptions {
+            seed: 3,
+            ..GameOptions::default()
+        }
+    }
+
+    /// Returns a game whose first hard drop clears a double.
+    fn double_game() -> Game {
+        let mut puzzle = Puzzle {
+            name: String::new(),
+            grid: vec![vec![None; 10]; 20],
+            queue: vec![BLOCK_O; 3],
+            hold_allowed: true,
+            objective: Objective::Lines(100),
+        };
+        let columns = Game::new_puzzle(&puzzle)
+            .get_current()
+            .cells()
+            .iter()
+            .map(|cell| cell.x)
+            .collect::<Vec<_>>();
+        for row in &mut puzzle.grid[18..] {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !columns.contains(&(x as i16)) {
+                    *cell = Some(PieceKind::Z);
+                }
+            }
+        }
+        // Not a perfect clear
+        puzzle.grid[17][if columns.contains(&0) { 9 } else { 0 }] = Some(PieceKind::Z);
+        Game::new_puzzle(&puzzle)
+    }
+
+    #[test]
+    fn test_same_pieces() {
+        let versus = Versus::new(options());
+        assert_eq!(
+            versus.game(0).get_previews(5),
+            versus.game(1).get_previews(5)
+        );
+    }
+
+    #[test]
+    fn test_same_pieces_after_garbage() {
+        let mut versus = Versus::new(options());
+        versus.game_mut(1).receive_garbage(2);
+        for player in 0..2 {
+            versus.game_mut(player).press(Action::HardDrop);
+        }
+        let height = versus.game(1).height() as usize;
+        assert!(crate::garbage::is_garbage_row(
+            &versus.game(1).get_grid()[height - 1]
+        ));
+        assert_eq!(versus.game(0).get_current(), versus.game(1).get_current());
+        assert_eq!(
+            versus.game(0).get_previews(5),
+            versus.game(1).get_previews(5)
+        );
+    }
+
+    #[test]
+    fn test_round() {
+        let mut versus = Versus::new(options());
+        while versus.outcome().is_none() {
+            versus.game_mut(1).press(Action::HardDrop);
+            versus.step();
+        }
+        assert_eq!(versus.outcome(), Some(Outcome::Winner(0)));
+        assert_eq!(versus.get_wins(), [1, 0]);
+
+        // The finished round is counted once
+        versus.step();
+        assert_eq!(versus.get_wins(), [1, 0]);
+
+        versus.next_round(options());
+        assert_eq!(versus.outcome(), None);
+        assert_eq!(versus.get_wins(), [1, 0]);
+    }
+
+    #[test]
+    fn test_against_cpu() {
+        let mut versus = Versus::against_cpu(options(), Difficulty::MEDIUM);
+        assert!(versus.has_cpu());
+        for _ in 0..300 {
+            versus.step();
+        }
+        assert!(versus.game(1).get_stats().pieces > 0);
+        assert_eq!(versus.game(0).get_stats().pieces, 0);
+    }
+
+    #[test]
+    fn test_garbage_exchange() {
+        let mut versus = Versus::new(options());
+        versus.games[0] = double_game();
+        versus.game_mut(0).press(Action::HardDrop);
+        assert_eq!(versus.game(0).get_lines(), 2);
+        versus.step();
+        assert_eq!(versus.game(1).get_incoming_garbage(), 1);
+
+        let height = versus.game(1).height() as usize;
+        versus.game_mut(1).press(Action::HardDrop);
+        let grid = versus.game(1).get_grid();
+        assert!(crate::garbage::is_garbage_row(&grid[height - 1]));
+        assert_eq!(versus.game(1).get_incoming_garbage(), 0);
+    }
+}